@@ -0,0 +1,24 @@
+// генерирует C-заголовок для src/ffi.rs в $OUT_DIR, дерево исходников не трогает;
+// постоянный путь для C-сборки: FOLD_PATTERN_INCLUDE_DIR=include cargo build
+
+fn main() {
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-env-changed=FOLD_PATTERN_INCLUDE_DIR");
+
+    let dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let out = std::env::var("OUT_DIR").unwrap();
+    let config = cbindgen::Config::from_file("cbindgen.toml").expect("bad cbindgen.toml");
+
+    match cbindgen::generate_with_config(&dir, config) {
+        Ok(bindings) => {
+            bindings.write_to_file(std::path::Path::new(&out).join("fold_pattern.h"));
+            // относительный путь - от корня пакета
+            if let Ok(inc) = std::env::var("FOLD_PATTERN_INCLUDE_DIR") {
+                bindings.write_to_file(std::path::Path::new(&dir).join(inc).join("fold_pattern.h"));
+            }
+        }
+        // заголовок не критичен для сборки самой библиотеки
+        Err(e) => println!("cargo:warning=cbindgen: {e}"),
+    }
+}
@@ -4,11 +4,29 @@
 pub enum Stmt {
     Conversation(Expr),  // applicable for Convert expr
     Decomposition(Expr), // applicable for Fraction and Unit expr
+    Definition {
+        // def tag = val expr
+        tag: String,
+        val: Value,
+        expr: Expr,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Uncertainty {
+    Abs(f64), // 12.5±0.2
+    Rel(f64), // 12.5±1.6%, хранится долей: 0.016
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Value {
+    pub v: f64,
+    pub u: Option<Uncertainty>,
 }
 
 #[derive(Debug)]
 pub enum Expr {
-    Convert(f64, Box<Expr>, Box<Expr>),
+    Convert(Value, Box<Expr>, Box<Expr>),
     Fraction {
         up: Vec<Expr>,
         down: Vec<Expr>,
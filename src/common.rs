@@ -1,5 +1,11 @@
+use crate::folder::UnitsError;
+use crate::interpreter::Interpreter;
+use crate::parser::ParseError;
 use crate::register::units;
+use crate::ucum::UcumError;
 use crate::register;
+use serde_json::{json, Value};
+use std::error::Error;
 
 pub const ACCURACY: usize = 10;
 
@@ -14,3 +20,52 @@ pub fn all_prefixes() -> String {
         .collect::<Vec<_>>()
         .join(", ")
 }
+
+/// Имя варианта ошибки для протоколов (server, rpc, wasm): NotCoherent, TooMuchSpaces, ...
+pub trait ErrorKind {
+    fn kind(&self) -> &'static str;
+}
+
+// ErrorKind по списку вариантов; после ";" - вариант-обертка, имя берется у вложенной ошибки
+macro_rules! impl_error_kind {
+    ($t:ident: $($v:ident),+ $(; $inner:ident)?) => {
+        impl $crate::common::ErrorKind for $t {
+            fn kind(&self) -> &'static str {
+                match self {
+                    $($t::$v { .. } => stringify!($v),)+
+                    $($t::$inner(e) => $crate::common::ErrorKind::kind(e),)?
+                }
+            }
+        }
+    };
+}
+pub(crate) use impl_error_kind;
+
+pub fn error_kind(e: &(dyn Error + 'static)) -> &'static str {
+    // имя варианта ParseError / UnitsError / UcumError: NotCoherent, TooMuchSpaces, ...
+    if let Some(pe) = e.downcast_ref::<ParseError>() {
+        pe.kind()
+    } else if let Some(ue) = e.downcast_ref::<UnitsError>() {
+        ue.kind()
+    } else if let Some(ue) = e.downcast_ref::<UcumError>() {
+        ue.kind()
+    } else {
+        "BadRequest"
+    }
+}
+
+// ответы convert/decompose для server, rpc и wasm
+
+pub(crate) fn convert_json(ii: &mut Interpreter, stmt: &str) -> Result<Value, Box<dyn Error>> {
+    let res = ii.try_conv_measured(stmt)?;
+    let result = ii.conv_result(stmt, &res);
+    Ok(json!({"value": res.v, "uncertainty": res.u, "result": result, "vocabularies": ii.state.vocs}))
+}
+
+pub(crate) fn decompose_json(ii: &mut Interpreter, expr: &str) -> Result<Value, Box<dyn Error>> {
+    let res = ii.deco(expr)?;
+    let dims: serde_json::Map<String, Value> = ii.state.units.values()
+        .map(|u| (u.tag.clone(), json!(u.pow)))
+        .collect();
+    Ok(json!({"mpl": ii.state.mpl, "units": dims, "result": res, "vocabularies": ii.state.vocs}))
+}
@@ -0,0 +1,176 @@
+// Complete: подсказки для частично введённого выражения (виджеты ввода, REPL)
+//
+// "1 к_П"     -> PrefixedUnit, token "к_П": к_Па
+// "кг/м^2*с"  -> Unit, token "с": с, сут, см_... а также операторы ^ *
+
+use crate::parser::{CONV, DEF, DIV, EQ, MUL, PM, PW, US, WS};
+use crate::register::{prefixes, units};
+use serde::Serialize;
+
+/// Где в грамматике находится курсор (конец ввода)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum State {
+    // число перед пробелом: 1, 1.5±0.1
+    Value,
+    // имя единицы или приставка
+    Unit,
+    // единица после приставки: к_П
+    PrefixedUnit,
+    // показатель степени после ^
+    Pow,
+    // def <tag>
+    DefTag,
+    // def tag <=>
+    DefEq,
+    // ввод уже не может стать корректным
+    Invalid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum CandidateKind {
+    Unit,
+    Prefix,
+    Operator,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Candidate {
+    pub text: String,
+    pub kind: CandidateKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Completion {
+    pub state: State,
+    // дописываемый фрагмент в конце ввода; Unit и Prefix заменяют его, Operator дописывается
+    pub token: String,
+    pub candidates: Vec<Candidate>,
+}
+
+impl Candidate {
+    fn new(text: impl Into<String>, kind: CandidateKind) -> Candidate {
+        Self { text: text.into(), kind }
+    }
+}
+
+impl Completion {
+    fn new(state: State, token: &str, candidates: Vec<Candidate>) -> Completion {
+        Self { state, token: token.to_owned(), candidates }
+    }
+
+    /// Ввод после выбора кандидата
+    pub fn apply(&self, input: &str, c: &Candidate) -> String {
+        match c.kind {
+            CandidateKind::Operator => format!("{input}{}", c.text),
+            _ => format!("{}{}", &input[..input.len() - self.token.len()], c.text),
+        }
+    }
+}
+
+pub fn complete(input: &str) -> Completion {
+    if input.starts_with(DEF) && input.contains(WS) {
+        return complete_def(input);
+    }
+
+    match input.split_once(WS) {
+        Some((_, rest)) if rest.contains(WS) => Completion::new(State::Invalid, "", vec![]),
+        // [float][WS][src][CONV][dst]
+        Some((_, rest)) => match rest.split_once(CONV) {
+            Some((_, dst)) => complete_expr(dst, false),
+            None => complete_expr(rest, true),
+        },
+        None if starts_as_value(input) => complete_value(input),
+        None => complete_expr(input, false),
+    }
+}
+
+fn complete_def(input: &str) -> Completion {
+    let parts: Vec<&str> = input.split(WS).collect();
+    match parts.as_slice() {
+        [_, tag] => Completion::new(State::DefTag, tag, vec![]),
+        [_, _, eq] if EQ.starts_with(eq) => {
+            let ops = if eq.is_empty() { vec![Candidate::new(EQ, CandidateKind::Operator)] } else { vec![] };
+            Completion::new(State::DefEq, eq, ops)
+        }
+        [_, _, EQ, val] => complete_value(val),
+        [_, _, EQ, _, expr] => complete_expr(expr, false),
+        _ => Completion::new(State::Invalid, "", vec![]),
+    }
+}
+
+fn starts_as_value(input: &str) -> bool {
+    input.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '+' || c == '.')
+}
+
+fn complete_value(val: &str) -> Completion {
+    let mut ops = vec![];
+    if val.split_once(PM).map_or(val, |(v, _)| v).parse::<f64>().is_ok() {
+        if !val.contains(PM) {
+            ops.push(Candidate::new(PM, CandidateKind::Operator));
+        }
+        ops.push(Candidate::new(WS, CandidateKind::Operator));
+    }
+    Completion::new(State::Value, val, ops)
+}
+
+fn complete_expr(expr: &str, src: bool) -> Completion {
+    // текущая единица - всё после последнего оператора
+    let token = expr.rsplit(|c| MUL.starts_with(c) || DIV.starts_with(c)).next().unwrap_or_default();
+
+    // операторы, допустимые после законченной единицы
+    let mut ops = vec![MUL];
+    if !expr.contains(DIV) {
+        ops.push(DIV);
+    }
+    if src {
+        ops.push(CONV);
+    }
+    let ops = |pow: bool| {
+        let mut c: Vec<Candidate> = vec![];
+        if pow {
+            c.push(Candidate::new(PW, CandidateKind::Operator));
+        }
+        c.extend(ops.iter().map(|op| Candidate::new(*op, CandidateKind::Operator)));
+        c
+    };
+
+    if let Some((_, pow)) = token.split_once(PW) {
+        let candidates = match pow.parse::<i8>() {
+            Ok(_) => ops(false),
+            Err(_) => vec![],
+        };
+        return Completion::new(State::Pow, token, candidates);
+    }
+
+    let voc = units();
+    let mut tags: Vec<&String> = voc.keys().collect();
+    tags.sort();
+
+    if let Some((pfx, tag)) = token.split_once(US) {
+        if !prefixes().contains_key(pfx) {
+            return Completion::new(State::Invalid, token, vec![]);
+        }
+        let mut candidates: Vec<Candidate> = tags.iter()
+            .filter(|t| t.starts_with(tag))
+            .map(|t| Candidate::new(format!("{pfx}{US}{t}"), CandidateKind::Unit))
+            .collect();
+        if voc.contains_key(tag) {
+            candidates.extend(ops(true));
+        }
+        return Completion::new(State::PrefixedUnit, token, candidates);
+    }
+
+    let mut candidates: Vec<Candidate> = tags.iter()
+        .filter(|t| t.starts_with(token))
+        .map(|t| Candidate::new(t.as_str(), CandidateKind::Unit))
+        .collect();
+
+    let mut pfxs: Vec<&str> = prefixes().into_keys().filter(|p| p.starts_with(token)).collect();
+    pfxs.sort();
+    candidates.extend(pfxs.iter().map(|p| Candidate::new(format!("{p}{US}"), CandidateKind::Prefix)));
+
+    if voc.contains_key(token) {
+        candidates.extend(ops(true));
+    }
+    Completion::new(State::Unit, token, candidates)
+}
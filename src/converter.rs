@@ -0,0 +1,121 @@
+// Converter: "src=>dst" compiled once, applied to many values
+
+use crate::folder::UnitsError;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Converter {
+    // конвертация линейная: аффинных шкал (°C) в словарях нет,
+    // логарифмические compile отвергает (NotLinear)
+    pub factor: f64,
+}
+
+impl Converter {
+    pub fn new(factor: f64) -> Converter {
+        Self { factor }
+    }
+
+    #[inline]
+    pub fn apply(&self, v: f64) -> f64 {
+        v * self.factor
+    }
+
+    pub fn apply_slice(&self, src: &[f64], dst: &mut [f64]) -> Result<(), UnitsError> {
+        if src.len() != dst.len() {
+            return Err(UnitsError::LengthMismatch(src.len(), dst.len()));
+        }
+        for (d, s) in dst.iter_mut().zip(src) {
+            *d = self.apply(*s);
+        }
+        Ok(())
+    }
+
+    pub fn apply_in_place(&self, vals: &mut [f64]) {
+        for v in vals.iter_mut() {
+            *v = self.apply(*v);
+        }
+    }
+
+    pub fn apply_iter<I>(&self, vals: I) -> impl Iterator<Item = f64>
+    where
+        I: IntoIterator<Item = f64>,
+    {
+        let conv = *self;
+        vals.into_iter().map(move |v| conv.apply(v))
+    }
+}
+
+/// Множитель "src=>dst": обращается и компонуется с проверкой размерности
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConversionFactor {
+    // выражения в нормальной форме: к_Па, атм
+    pub src: String,
+    pub dst: String,
+    pub factor: f64,
+    // размерность в базовых единицах: [(кг, 1), (м, -1), (с, -2)]
+    pub dims: Vec<(String, i8)>,
+    // множитель dst к базовым единицам, нужен для then_rescaled
+    dst_mpl: f64,
+}
+
+impl ConversionFactor {
+    pub(crate) fn new(src: String, dst: String, factor: f64, dims: Vec<(String, i8)>, dst_mpl: f64) -> ConversionFactor {
+        Self { src, dst, factor, dims, dst_mpl }
+    }
+
+    #[inline]
+    pub fn apply(&self, v: f64) -> f64 {
+        v * self.factor
+    }
+
+    /// dst=>src
+    pub fn inverse(&self) -> ConversionFactor {
+        Self {
+            src: self.dst.clone(),
+            dst: self.src.clone(),
+            factor: 1.0 / self.factor,
+            dims: self.dims.clone(),
+            dst_mpl: self.factor * self.dst_mpl,
+        }
+    }
+
+    /// src=>dst, затем other: dst=>other.dst; other.src должен совпадать с dst,
+    /// так что a.then(b).apply(v) == b.apply(a.apply(v))
+    pub fn then(&self, other: &ConversionFactor) -> Result<ConversionFactor, UnitsError> {
+        self.check_dims(other)?;
+        if self.dst != other.src {
+            return Err(UnitsError::ChainMismatch(self.dst.clone(), other.src.clone()));
+        }
+        Ok(self.chain(other, self.factor * other.factor))
+    }
+
+    /// как then, но разные промежуточные единицы одной размерности пересчитываются:
+    /// к_Па=>атм затем Па=>ат даёт к_Па=>ат
+    pub fn then_rescaled(&self, other: &ConversionFactor) -> Result<ConversionFactor, UnitsError> {
+        self.check_dims(other)?;
+        // self.dst => other.src: self.dst_mpl / (other.factor * other.dst_mpl)
+        let link = self.dst_mpl / (other.factor * other.dst_mpl);
+        Ok(self.chain(other, self.factor * link * other.factor))
+    }
+
+    fn check_dims(&self, other: &ConversionFactor) -> Result<(), UnitsError> {
+        if self.dims != other.dims {
+            let show = |d: &[(String, i8)]| d.iter().map(|(t, p)| format!("{t}^{p}")).collect::<Vec<_>>().join("*");
+            return Err(UnitsError::NotCoherent(show(&self.dims), show(&other.dims)));
+        }
+        Ok(())
+    }
+
+    fn chain(&self, other: &ConversionFactor, factor: f64) -> ConversionFactor {
+        Self {
+            src: self.src.clone(),
+            dst: other.dst.clone(),
+            factor,
+            dims: self.dims.clone(),
+            dst_mpl: other.dst_mpl,
+        }
+    }
+
+    pub fn converter(&self) -> Converter {
+        Converter::new(self.factor)
+    }
+}
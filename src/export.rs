@@ -0,0 +1,154 @@
+// Export: выгрузка словаря - CSV (единица, множитель, размерность СИ),
+// справочник Markdown и UCUM-подобные определения. Всё считается через to_bases.
+
+use crate::ucum::atom_code;
+use crate::units::{canonical, to_bases, Unit, UNITY};
+use std::collections::{BTreeMap, HashMap};
+
+struct Row<'a> {
+    unit: &'a Unit,
+    // множитель к базовым единицам СИ
+    factor: f64,
+    // размерность: базовая единица и степень
+    dims: Vec<(String, i8)>,
+}
+
+fn rows(voc: &HashMap<String, Unit>) -> Vec<Row<'_>> {
+    let mut tags: Vec<&String> = voc.keys().collect();
+    tags.sort();
+
+    // степени словарных определений малы, переполнение i8 тут невозможно на практике
+    tags.into_iter()
+        .filter_map(|tag| {
+            let unit = &voc[tag];
+            let (factor, base) = to_bases(unit, voc).ok()?;
+            Some(Row { unit, factor, dims: merge_dims(base) })
+        })
+        .collect()
+}
+
+fn merge_dims(base: Vec<Unit>) -> Vec<(String, i8)> {
+    // to_bases может вернуть одну базовую единицу несколько раз: м * м
+    canonical(base).into_iter()
+        .filter(|u| u.tag != UNITY)
+        .map(|u| (u.tag, u.pow))
+        .collect()
+}
+
+fn dims_dsl(dims: &[(String, i8)]) -> String {
+    // в синтаксисе DSL, в нормальной форме: кг*м^-1*с^-2
+    if dims.is_empty() {
+        return UNITY.to_owned();
+    }
+    dims.iter()
+        .map(|(t, pow)| if *pow == 1 { t.clone() } else { format!("{t}^{pow}") })
+        .collect::<Vec<_>>()
+        .join("*")
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+/// tag,factor,dimension - по единице на строку, логарифмические единицы пропускаются
+pub fn to_csv(voc: &HashMap<String, Unit>) -> String {
+    let mut out = String::from("tag,factor,dimension\n");
+    for r in rows(voc).iter().filter(|r| r.unit.log.is_none()) {
+        out += &format!("{},{},{}\n", csv_field(&r.unit.tag), r.factor, csv_field(&dims_dsl(&r.dims)));
+    }
+    out
+}
+
+fn md_cell(s: &str) -> String {
+    s.replace('|', "\\|")
+}
+
+/// Справочник: таблица на каждый словарь (name@version)
+pub fn to_markdown(voc: &HashMap<String, Unit>) -> String {
+    let mut groups: BTreeMap<String, Vec<Row>> = BTreeMap::new();
+    for r in rows(voc) {
+        let name = r.unit.voc.clone().unwrap_or("runtime".into());
+        groups.entry(name).or_default().push(r);
+    }
+
+    let mut out = String::from("# Справочник единиц\n");
+    for (name, rows) in groups {
+        out += &format!("\n## {name}\n\n");
+        out += "| Единица | Обозначение | Наименование | Род | Множитель | Размерность СИ | Примечание |\n";
+        out += "|---|---|---|---|---|---|---|\n";
+
+        for r in rows {
+            let u = r.unit;
+            let mut notes = vec![];
+            if u.log.is_some() {
+                notes.push("логарифмическая".to_owned());
+            }
+            if !u.is_exact() {
+                notes.push(format!("неточное определение, δ = {}", u.unc));
+            }
+            if let Some(d) = &u.meta.deprecated {
+                notes.push(format!("не рекомендуется: {d}"));
+            }
+            let factor = match u.log {
+                Some(_) => "-".to_owned(),
+                None => r.factor.to_string(),
+            };
+            let cells = [
+                u.tag.as_str(),
+                u.meta.symbol.as_deref().unwrap_or_default(),
+                u.meta.description.as_deref().unwrap_or_default(),
+                u.kind.as_deref().unwrap_or_default(),
+                &factor,
+                &format!("`{}`", dims_dsl(&r.dims)),
+                &notes.join("; "),
+            ];
+            let cells: Vec<String> = cells.iter().map(|c| md_cell(c)).collect();
+            out += &format!("| {} |\n", cells.join(" | "));
+        }
+    }
+    out
+}
+
+fn ucum_symbol(tag: &str, voc: &HashMap<String, Unit>) -> String {
+    // код UCUM (Ом -> Ohm, как при разборе), затем международное обозначение,
+    // иначе тег в скобках, как произвольные единицы UCUM: [кгс]
+    if let Some(code) = atom_code(tag) {
+        return code.to_owned();
+    }
+    match voc.get(tag).and_then(|u| u.meta.symbol.clone()) {
+        Some(s) => s,
+        None if tag == UNITY => UNITY.to_owned(),
+        None => format!("[{tag}]"),
+    }
+}
+
+fn dims_ucum(dims: &[(String, i8)], voc: &HashMap<String, Unit>) -> String {
+    // kg.m-1.s-2
+    if dims.is_empty() {
+        return UNITY.to_owned();
+    }
+    dims.iter()
+        .map(|(t, pow)| match pow {
+            1 => ucum_symbol(t, voc),
+            _ => format!("{}{pow}", ucum_symbol(t, voc)),
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// UCUM-подобные определения: kgf = 9.80665 m.kg.s-2 # кгс
+pub fn to_ucum(voc: &HashMap<String, Unit>) -> String {
+    let mut out = String::new();
+    for r in rows(voc).iter().filter(|r| r.unit.log.is_none()) {
+        let sym = ucum_symbol(&r.unit.tag, voc);
+        out += &match r.unit.base.is_empty() {
+            true => format!("{sym} # {}, base\n", r.unit.tag),
+            false => format!("{sym} = {} {} # {}\n", r.factor, dims_ucum(&r.dims, voc), r.unit.tag),
+        };
+    }
+    out
+}
@@ -0,0 +1,241 @@
+// FFI: C ABI для использования конвертера из C/C++/Python(ctypes)
+//
+// UcRegistry *reg = uc_registry_new();
+// double v;
+// if (uc_convert(reg, "1 к_Па=>атм", &v) != UC_STATUS_OK)
+//     puts(uc_last_error(reg));
+// uc_registry_free(reg);
+//
+// заголовок генерируется build.rs в $OUT_DIR/fold_pattern.h
+
+use crate::folder::UnitsError;
+use crate::interpreter::Interpreter;
+use crate::parser::ParseError;
+use crate::register::init_embedded_units;
+use std::error::Error;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+/// Код возврата: 0 - успех, 1xx - ошибки разбора (ParseError),
+/// 2xx - ошибки единиц (UnitsError), 998 - паника внутри библиотеки
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UcStatus {
+    Ok = 0,
+    NullArg = 1,
+    InvalidUtf8 = 2,
+
+    TooMuchSpaces = 101,
+    WrongUnit = 102,
+    ValueWrongBegin = 103,
+    WrongUncertainty = 104,
+    ExactlyOneExprSeparator = 105,
+    UnknownPrefix = 106,
+    UnknownUnit = 107,
+    WrongPow = 108,
+    WrongDefinition = 109,
+
+    NotCoherent = 201,
+    NoUnit = 202,
+    NoUnitPrefix = 203,
+    LogInProduct = 204,
+    KindMismatch = 205,
+    NotLinear = 206,
+    LengthMismatch = 207,
+    UnitExists = 208,
+    CyclicDefinition = 209,
+    ChainMismatch = 210,
+    PowOverflow = 211,
+
+    Internal = 998,
+    Other = 999,
+}
+
+impl From<&ParseError> for UcStatus {
+    fn from(e: &ParseError) -> Self {
+        match e {
+            ParseError::TooMuchSpaces(_) => UcStatus::TooMuchSpaces,
+            ParseError::WrongUnit(_) => UcStatus::WrongUnit,
+            ParseError::ValueWrongBegin(_) => UcStatus::ValueWrongBegin,
+            ParseError::WrongUncertainty(_) => UcStatus::WrongUncertainty,
+            ParseError::ExactlyOneExprSeparator() => UcStatus::ExactlyOneExprSeparator,
+            ParseError::UnknownPrefix(_) => UcStatus::UnknownPrefix,
+            ParseError::UnknownUnit(_) => UcStatus::UnknownUnit,
+            ParseError::WrongPow(_) => UcStatus::WrongPow,
+            ParseError::WrongDefinition(_) => UcStatus::WrongDefinition,
+        }
+    }
+}
+
+impl From<&UnitsError> for UcStatus {
+    fn from(e: &UnitsError) -> Self {
+        match e {
+            UnitsError::NotCoherent(..) => UcStatus::NotCoherent,
+            UnitsError::NoUnit(_) => UcStatus::NoUnit,
+            UnitsError::NoUnitPrefix(_) => UcStatus::NoUnitPrefix,
+            UnitsError::LogInProduct(_) => UcStatus::LogInProduct,
+            UnitsError::KindMismatch(..) => UcStatus::KindMismatch,
+            UnitsError::NotLinear(_) => UcStatus::NotLinear,
+            UnitsError::LengthMismatch(..) => UcStatus::LengthMismatch,
+            UnitsError::UnitExists(_) => UcStatus::UnitExists,
+            UnitsError::CyclicDefinition(_) => UcStatus::CyclicDefinition,
+            UnitsError::ChainMismatch(..) => UcStatus::ChainMismatch,
+            UnitsError::PowOverflow(_) => UcStatus::PowOverflow,
+        }
+    }
+}
+
+impl From<&(dyn Error + 'static)> for UcStatus {
+    fn from(e: &(dyn Error + 'static)) -> Self {
+        if let Some(e) = e.downcast_ref::<ParseError>() {
+            return e.into();
+        }
+        if let Some(e) = e.downcast_ref::<UnitsError>() {
+            return e.into();
+        }
+        UcStatus::Other
+    }
+}
+
+/// Непрозрачный для C контекст: интерпретатор и текст последней ошибки
+pub struct UcRegistry {
+    ii: Interpreter,
+    last_error: Option<CString>,
+}
+
+impl UcRegistry {
+    fn fail(&mut self, status: UcStatus, msg: String) -> UcStatus {
+        // строка из Display ошибки не содержит \0, но на всякий случай
+        self.last_error = CString::new(msg.replace('\0', "")).ok();
+        status
+    }
+
+    fn fail_with(&mut self, e: Box<dyn Error>) -> UcStatus {
+        self.fail(e.as_ref().into(), e.to_string())
+    }
+}
+
+pub(crate) unsafe fn with_reg(reg: *mut UcRegistry, f: impl FnOnce(&mut UcRegistry) -> UcStatus) -> UcStatus {
+    // паника не должна пересекать границу extern "C"; last_error - только от этого вызова
+    let Some(r) = reg.as_mut() else { return UcStatus::NullArg };
+    r.last_error = None;
+    match catch_unwind(AssertUnwindSafe(|| f(r))) {
+        Ok(status) => status,
+        Err(_) => (*reg).fail(UcStatus::Internal, "internal error".into()),
+    }
+}
+
+unsafe fn read_str<'a>(s: *const c_char) -> Result<&'a str, UcStatus> {
+    if s.is_null() {
+        return Err(UcStatus::NullArg);
+    }
+    CStr::from_ptr(s).to_str().map_err(|_| UcStatus::InvalidUtf8)
+}
+
+/// Создаёт контекст; словари вшиты в библиотеку. NULL при внутренней ошибке.
+/// Освобождать через uc_registry_free.
+#[no_mangle]
+pub extern "C" fn uc_registry_new() -> *mut UcRegistry {
+    catch_unwind(|| {
+        init_embedded_units();
+        let reg = UcRegistry { ii: Interpreter::new(), last_error: None };
+        Box::into_raw(Box::new(reg))
+    })
+    .unwrap_or(ptr::null_mut())
+}
+
+/// # Safety
+/// `reg` - указатель из uc_registry_new или NULL
+#[no_mangle]
+pub unsafe extern "C" fn uc_registry_free(reg: *mut UcRegistry) {
+    if !reg.is_null() {
+        let _ = catch_unwind(AssertUnwindSafe(|| drop(Box::from_raw(reg))));
+    }
+}
+
+/// Конвертирует `stmt` вида "1 к_Па=>атм", результат пишет в `out`.
+///
+/// # Safety
+/// `reg` - живой контекст, `stmt` - строка UTF-8 с \0 на конце, `out` - валидный указатель
+#[no_mangle]
+pub unsafe extern "C" fn uc_convert(
+    reg: *mut UcRegistry,
+    stmt: *const c_char,
+    out: *mut f64,
+) -> UcStatus {
+    with_reg(reg, |reg| {
+        if out.is_null() {
+            return reg.fail(UcStatus::NullArg, "out is NULL".into());
+        }
+        let stmt = match read_str(stmt) {
+            Ok(s) => s,
+            Err(st) => return reg.fail(st, "stmt is NULL or not UTF-8".into()),
+        };
+        match reg.ii.try_conv_f64(stmt) {
+            Ok(v) => {
+                *out = v;
+                UcStatus::Ok
+            }
+            Err(e) => reg.fail_with(e),
+        }
+    })
+}
+
+/// Раскладывает `expr` на базовые единицы: множитель в `mpl`,
+/// строку единиц вида "[кг^1 * м^-1 * с^-2]" в `units` (освобождать через uc_free).
+///
+/// # Safety
+/// `reg` - живой контекст, `expr` - строка UTF-8 с \0 на конце,
+/// `mpl` и `units` - валидные указатели
+#[no_mangle]
+pub unsafe extern "C" fn uc_decompose(
+    reg: *mut UcRegistry,
+    expr: *const c_char,
+    mpl: *mut f64,
+    units: *mut *mut c_char,
+) -> UcStatus {
+    with_reg(reg, |reg| {
+        if mpl.is_null() || units.is_null() {
+            return reg.fail(UcStatus::NullArg, "mpl or units is NULL".into());
+        }
+        let expr = match read_str(expr) {
+            Ok(s) => s,
+            Err(st) => return reg.fail(st, "expr is NULL or not UTF-8".into()),
+        };
+        match reg.ii.deco(expr) {
+            Ok(res) => {
+                let dims = res.split_once(' ').map(|(_, d)| d).unwrap_or_default();
+                *mpl = reg.ii.state.mpl;
+                *units = CString::new(dims).unwrap_or_default().into_raw();
+                UcStatus::Ok
+            }
+            Err(e) => reg.fail_with(e),
+        }
+    })
+}
+
+/// Текст ошибки последнего вызова или NULL, если он прошёл успешно;
+/// живёт до следующего вызова с этим контекстом.
+///
+/// # Safety
+/// `reg` - живой контекст или NULL
+#[no_mangle]
+pub unsafe extern "C" fn uc_last_error(reg: *const UcRegistry) -> *const c_char {
+    catch_unwind(|| match reg.as_ref().and_then(|r| r.last_error.as_ref()) {
+        Some(msg) => msg.as_ptr(),
+        None => ptr::null(),
+    })
+    .unwrap_or(ptr::null())
+}
+
+/// Освобождает строку, выделенную библиотекой.
+///
+/// # Safety
+/// `s` - строка из uc_decompose или NULL
+#[no_mangle]
+pub unsafe extern "C" fn uc_free(s: *mut c_char) {
+    if !s.is_null() {
+        let _ = catch_unwind(AssertUnwindSafe(|| drop(CString::from_raw(s))));
+    }
+}
@@ -1,6 +1,8 @@
 // Folder: transform parsed units to C-System of units
 
 use crate::ast::*;
+use crate::common::impl_error_kind;
+use crate::register::units;
 use crate::units::{BaseUnits, ParsedUnit};
 
 #[derive(Debug, thiserror::Error)]
@@ -13,13 +15,75 @@ pub enum UnitsError {
 
     #[error("unit prefix {0} not found")]
     NoUnitPrefix(String),
+
+    #[error("logarithmic unit {0} can only be converted standalone")]
+    LogInProduct(String),
+
+    #[error("quantity kinds differ:{0} <=> {1}")]
+    KindMismatch(String, String),
+
+    #[error("conversion is not linear: {0}")]
+    NotLinear(String),
+
+    #[error("slices length differ: {0} <=> {1}")]
+    LengthMismatch(usize, usize),
+
+    #[error("unit {0} already exists")]
+    UnitExists(String),
+
+    #[error("unit {0} is defined through itself")]
+    CyclicDefinition(String),
+
+    #[error("conversion chain is broken: {0} <=> {1}")]
+    ChainMismatch(String, String),
+
+    #[error("power of {0} is out of range")]
+    PowOverflow(String),
+}
+
+impl_error_kind!(UnitsError: NotCoherent, NoUnit, NoUnitPrefix, LogInProduct, KindMismatch, NotLinear,
+    LengthMismatch, UnitExists, CyclicDefinition, ChainMismatch, PowOverflow);
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FoldOptions {
+    // стирать псевдо-размерности (рад, ср) при проверке совместимости,
+    // т.е. разрешить конвертацию Гц <=> рад/с
+    pub erase_pseudo: bool,
+    // строгий режим: запрещает конвертацию между разными родами величин
+    // одной размерности (Дж <=> Н*м, Гц <=> Бк)
+    pub strict_kinds: bool,
+    // учитывать неопределенность множителей неточно определенных единиц
+    pub factor_unc: bool,
 }
 
 pub(crate) trait Folder {
+    fn options(&self) -> FoldOptions {
+        FoldOptions::default()
+    }
+
     fn fold_stmt(&mut self, s: &Stmt) -> Result<BaseUnits, UnitsError> {
         let folded = match s {
             Stmt::Conversation(conv) => self.fold_expr(conv)?,
             Stmt::Decomposition(expr) => self.fold_expr(expr)?,
+            Stmt::Definition { tag, val, expr } => {
+                if units().contains_key(tag) {
+                    return Err(UnitsError::UnitExists(tag.clone()));
+                }
+                if let Expr::Fraction { up, down } = expr {
+                    let cyclic = up.iter().chain(down.iter()).any(|u| {
+                        matches!(u, Expr::Unit { tag: t, .. } if t == tag)
+                    });
+                    if cyclic {
+                        return Err(UnitsError::CyclicDefinition(tag.clone()));
+                    }
+                }
+                let mut base = self.fold_expr(expr)?;
+                if base.log.is_some() {
+                    return Err(UnitsError::LogInProduct(tag.clone()));
+                }
+                base.v = (*val).into();
+                base
+            }
         };
         Ok(folded)
     }
@@ -28,15 +92,54 @@ pub(crate) trait Folder {
         match e {
             Expr::Convert(v, src, dst) => {
                 let mut base = BaseUnits::new();
-                base.v = *v;
+                base.v = (*v).into();
+
+                let mut src_base = self.fold_expr(src)?;
+                let mut dst_base = self.fold_expr(dst)?;
+
+                if self.options().erase_pseudo {
+                    src_base.erase_pseudo();
+                    dst_base.erase_pseudo();
+                }
 
-                let src_base = self.fold_expr(src)?;
-                let dst_base = self.fold_expr(dst)?;
+                if self.options().strict_kinds && !src_base.kind.accepts(&dst_base.kind) {
+                    return Err(UnitsError::KindMismatch(
+                        src_base.kind.to_string(),
+                        dst_base.kind.to_string(),
+                    ));
+                }
 
                 // coherent
                 if src_base.is_coherent(&dst_base) {
                     base.units = src_base.units;
                     base.mpl = src_base.mpl / dst_base.mpl;
+                    base.vocs = src_base.vocs;
+                    base.vocs.extend(dst_base.vocs);
+
+                    base.mpl_unc = src_base.mpl_unc.hypot(dst_base.mpl_unc);
+                    let rel = match self.options().factor_unc {
+                        true => base.mpl_unc,
+                        false => 0.0,
+                    };
+
+                    // логарифмические единицы: конвертация нелинейна,
+                    // поэтому результат считается сразу в v
+                    if src_base.log.is_some() || dst_base.log.is_some() {
+                        let x = match src_base.log {
+                            Some(l) => l.to_linear(base.v) * src_base.mpl,
+                            None => base.v * src_base.mpl,
+                        };
+                        let x = x.with_rel(rel);
+                        base.v = match dst_base.log {
+                            Some(l) => l.to_level(x / dst_base.mpl),
+                            None => x / dst_base.mpl,
+                        };
+                        base.mpl = 1.0;
+                        // в результате Convert log - признак нелинейной конвертации
+                        base.log = src_base.log.or(dst_base.log);
+                    } else {
+                        base.v = base.v.with_rel(rel);
+                    }
 
                 // not coherent
                 } else {
@@ -50,17 +153,26 @@ pub(crate) trait Folder {
 
             Expr::Fraction { up, down } => {
                 let mut base = BaseUnits::new();
+                let mut log = None;
 
                 for unit in up.iter().chain(down.iter()) {
                     match unit {
                         Expr::Unit {
-                            pfx: _,
+                            pfx: p,
                             tag: t,
-                            pow: _,
-                            den: _,
+                            pow: w,
+                            den: d,
                         } => {
                             let folded = self.fold_expr(unit)?;
-                            base.merge_one(folded, t.to_string());
+
+                            // логарифмическая единица допустима только сама по себе
+                            if let Some(l) = folded.units.get(t).and_then(|u| u.log) {
+                                if up.len() + down.len() > 1 || p.is_some() || *w != 1 || *d {
+                                    return Err(UnitsError::LogInProduct(t.to_string()));
+                                }
+                                log = Some(l);
+                            }
+                            base.merge_one(folded, t.to_string())?;
                         }
                         _ => return Err(UnitsError::NoUnit("sorry".into())),
                     }
@@ -71,9 +183,12 @@ pub(crate) trait Folder {
                 // (но уже в формате базовых единиц(pfx -> mpl)).
                 // reduce - приводит все единицы к самым базовым.
 
+                base.kind = base.expr_kind();
+
                 // mpl of base here is 1.0
-                base = base.reduce();
+                base = base.reduce()?;
                 // mpl of base here != 1.0
+                base.log = log;
                 Ok(base)
             }
 
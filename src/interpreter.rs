@@ -1,59 +1,236 @@
-use crate::folder::Folder;
-use crate::parser::{enter_validation, parse_stmt};
-use crate::units::{log_data, BaseUnits, Unit};
+use crate::ast::{Expr, Stmt, Value};
+use crate::converter::{ConversionFactor, Converter};
+use crate::folder::{FoldOptions, Folder, UnitsError};
+use crate::measured::Measured;
+use crate::parser::{enter_validation, parse_expr, parse_stmt, ParseError};
+use crate::register::{add_unit, prefixes, units};
+use crate::trace::{explain_stmt, Trace};
+use crate::ucum;
+use crate::unicode::{self, pretty};
+use crate::units::{self, base_from_expr, BaseUnits, ExprKind, Unit};
+use serde::Serialize;
 use std::error::Error;
 
-impl Folder for Interpreter {}
+impl Folder for Interpreter {
+    fn options(&self) -> FoldOptions {
+        self.opts
+    }
+}
+
+// единица той же размерности: 1 исходная = factor tag
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Compatible {
+    pub tag: String,
+    pub factor: f64,
+}
 
 pub struct Interpreter {
     pub state: BaseUnits,
+    pub opts: FoldOptions,
+    // вывод conv/deco с надстрочными степенями: кг·м⁻¹·с⁻²
+    pub pretty: bool,
+    // приставка без "_" у неизвестных тегов: руб/кВт*ч как руб/к_Вт*ч
+    pub split_prefixes: bool,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Interpreter {
     pub fn new() -> Interpreter {
-        Self { state: BaseUnits::new() }
+        Self {
+            state: BaseUnits::new(),
+            opts: FoldOptions::default(),
+            pretty: false,
+            split_prefixes: false,
+        }
+    }
+
+    fn normalize(&self, input: &str) -> String {
+        match self.split_prefixes {
+            true => unicode::normalize_split(input),
+            false => unicode::normalize(input),
+        }
+    }
+
+    pub fn exec(&mut self, stmt: &str) -> Result<String, Box<dyn Error>> {
+        // dispatch any statement
+        let stmt = &self.normalize(stmt);
+        if stmt.starts_with("def ") {
+            self.define(stmt)
+        } else if stmt.contains("=>") {
+            self.try_conv(stmt)
+        } else {
+            self.deco(stmt)
+        }
     }
 
     pub fn conv_f64(&mut self, stmt: &str) -> Result<f64, Box<dyn Error>> {
+        Ok(self.conv_measured(stmt)?.v)
+    }
+
+    pub fn conv_measured(&mut self, stmt: &str) -> Result<Measured, Box<dyn Error>> {
         // Calculate given conversation
-        enter_validation(stmt)?;
-        let stmt_ast = parse_stmt(stmt)?;
-        match self.fold_stmt(&stmt_ast) {
-            Ok(conv) => { Ok(conv.v * conv.mpl) }
-            Err(err) => {
+        // ошибки единиц печатаются, результат 0 (поведение REPL); см. try_conv_measured
+        match self.try_conv_measured(stmt) {
+            Err(err) if err.is::<UnitsError>() => {
                 println!("{}", err);
-                Ok(0.0)
+                Ok(Measured::exact(0.0))
             }
+            res => res,
+        }
+    }
+
+    pub fn try_conv_f64(&mut self, stmt: &str) -> Result<f64, Box<dyn Error>> {
+        Ok(self.try_conv_measured(stmt)?.v)
+    }
+
+    pub fn try_conv_measured(&mut self, stmt: &str) -> Result<Measured, Box<dyn Error>> {
+        // как conv_measured, но любая ошибка возвращается вызывающему
+        let stmt = &self.normalize(stmt);
+        enter_validation(stmt)?;
+        let stmt_ast = parse_stmt(stmt)?;
+        let conv = self.fold_stmt(&stmt_ast)?;
+        let res = conv.v * conv.mpl;
+        self.state = conv;
+        Ok(res)
+    }
+
+    pub fn compile(&mut self, stmt: &str) -> Result<Converter, Box<dyn Error>> {
+        // compile "src=>dst" (without value) once for batch conversion
+        Ok(self.compile_factor(stmt)?.converter())
+    }
+
+    pub fn compile_factor(&mut self, stmt: &str) -> Result<ConversionFactor, Box<dyn Error>> {
+        // "src=>dst" -> множитель с размерностью, для inverse/then
+        let stmt = &self.normalize(stmt);
+        let (src, dst) = match stmt.split_once("=>") {
+            Some((src, dst)) if !dst.contains("=>") => (src, dst),
+            _ => return Err(Box::new(ParseError::ExactlyOneExprSeparator())),
+        };
+        let one = Value { v: 1.0, u: None };
+        let conv = Expr::Convert(one, Box::new(parse_expr(src)?), Box::new(parse_expr(dst)?));
+
+        let conv = self.fold_expr(&conv)?;
+        if conv.log.is_some() {
+            return Err(Box::new(UnitsError::NotLinear(stmt.to_owned())));
         }
+        let dst_mpl = self.fold_expr(&parse_expr(dst)?)?.mpl;
+        let dims = conv.canonical().into_iter().map(|u| (u.tag, u.pow)).collect();
+        Ok(ConversionFactor::new(units::normalize(src)?, units::normalize(dst)?, conv.mpl, dims, dst_mpl))
+    }
+
+    pub fn explain(&mut self, stmt: &str) -> Result<(BaseUnits, Trace), Box<dyn Error>> {
+        // result with step by step trace of multiplier calculation
+        let stmt = &self.normalize(stmt);
+        enter_validation(stmt)?;
+        let stmt_ast = parse_stmt(stmt)?;
+        let res = self.fold_stmt(&stmt_ast)?;
+        let trace = explain_stmt(stmt, &stmt_ast, &units())?;
+        Ok((res, trace))
     }
 
     pub fn conv(&mut self, stmt: &str) -> Result<String, Box<dyn Error>> {
-        let res = self.conv_f64(stmt)?;
-        let res = format!("{:e}", res);
-        let (l, r) = stmt.split_once("=>").unwrap();
-        Ok(format!("{l} => {res} {r}"))
+        let res = self.conv_measured(stmt)?;
+        Ok(self.conv_result(stmt, &res))
     }
 
-    pub fn deco(&mut self, stmt: &str) -> Result<String, Box<dyn Error>> {
+    pub fn try_conv(&mut self, stmt: &str) -> Result<String, Box<dyn Error>> {
+        let res = self.try_conv_measured(stmt)?;
+        Ok(self.conv_result(stmt, &res))
+    }
+
+    pub fn conv_result(&self, stmt: &str, res: &Measured) -> String {
+        // "1 атм => 1.01325e5 Па" для уже вычисленного результата stmt
+        let stmt = self.normalize(stmt);
+        let (l, r) = stmt.split_once("=>").unwrap_or((&stmt, ""));
+        let res = format!("{l} => {res} {r}");
+        if self.pretty { pretty(&res) } else { res }
+    }
+
+    pub fn define(&mut self, stmt: &str) -> Result<String, Box<dyn Error>> {
+        // add unit to registry: def узел = 1852 м/ч
+        let stmt = &self.normalize(stmt);
         enter_validation(stmt)?;
 
         let stmt = parse_stmt(stmt)?;
-        let deco = self.fold_stmt(&stmt)?;
-        self.state = deco.clone();
+        let def = self.fold_stmt(&stmt)?;
 
-        let mut rf: Vec<Unit> = deco.units.values().cloned().
-            into_iter().map(|x| x).collect::<Vec<Unit>>();
-        let mpl = format!("{:.8}", self.state.mpl);
+        let Stmt::Definition { tag, val, expr } = stmt else {
+            return Err(Box::new(ParseError::WrongDefinition(format!("{stmt:?}"))));
+        };
+        let mut unit = Unit::new(&tag, 1.0, 1);
+        unit.base = base_from_expr(val.v, &expr)?;
+        unit.unc = def.v.rel();
+        if let ExprKind::Of(k) = def.kind {
+            unit.kind = Some(k);
+        }
+        add_unit(unit);
+
+        Ok(format!("{tag} = {}", self.deco(&tag)?))
+    }
+
+    pub fn ucum(&mut self, stmt: &str) -> Result<BaseUnits, Box<dyn Error>> {
+        // statement с кодами UCUM: "1 kPa=>mm[Hg]", "kg.m/s2"
+        let stmt = ucum::parse_stmt(stmt)?;
+        self.state = self.fold_stmt(&stmt)?;
+        Ok(self.state.clone())
+    }
+
+    pub fn compatible_units(&mut self, expr: &str, prefixed: bool) -> Result<Vec<Compatible>, Box<dyn Error>> {
+        // все единицы словаря (и, опционально, с приставками) той же размерности, что expr
+        let expr = &self.normalize(expr);
+        let mut src = self.fold_stmt(&Stmt::Decomposition(parse_expr(expr)?))?;
+        if src.log.is_some() {
+            return Err(Box::new(UnitsError::NotLinear(expr.to_owned())));
+        }
+        if self.opts.erase_pseudo {
+            src.erase_pseudo();
+        }
+
+        let tags: Vec<String> = units().keys().cloned().collect();
+        let mut res = vec![];
+
+        for tag in tags {
+            let Ok(mut dst) = self.fold_stmt(&Stmt::Decomposition(parse_expr(&tag)?)) else {
+                continue;
+            };
+            if self.opts.erase_pseudo {
+                dst.erase_pseudo();
+            }
+            if dst.log.is_some() || !src.is_coherent(&dst) {
+                continue;
+            }
+            if self.opts.strict_kinds && !src.kind.accepts(&dst.kind) {
+                continue;
+            }
 
-        // сортируем в целях более простых
-        // ассертов в тестах с использованием .to_string
-        rf.sort_by(|a, b| {
-            a.tag.cmp(&b.tag)
-                .then(a.pow.cmp(&b.pow))
-                .then(a.mpl.partial_cmp(&b.mpl).unwrap())
-        });
+            let factor = src.mpl / dst.mpl;
+            if prefixed && units().get(&tag).is_some_and(Unit::accepts_prefixes) {
+                for (pfx, exp) in prefixes() {
+                    let tag = format!("{pfx}_{tag}");
+                    res.push(Compatible { tag, factor: factor / 10f64.powi(exp) });
+                }
+            }
+            res.push(Compatible { tag, factor });
+        }
 
-        let res = format!("{} {}", mpl, log_data(&rf));
+        res.sort_by(|a, b| a.tag.cmp(&b.tag));
         Ok(res)
     }
+
+    pub fn deco(&mut self, stmt: &str) -> Result<String, Box<dyn Error>> {
+        let stmt = &self.normalize(stmt);
+        enter_validation(stmt)?;
+
+        let stmt = parse_stmt(stmt)?;
+        self.state = self.fold_stmt(&stmt)?;
+
+        let mpl = format!("{:.8}", self.state.mpl);
+        let res = format!("{} {}", mpl, self.state.as_readable());
+        Ok(if self.pretty { pretty(&res) } else { res })
+    }
 }
@@ -0,0 +1,1743 @@
+pub mod ast;
+pub mod common;
+pub mod complete;
+pub mod converter;
+pub mod export;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod ffi;
+pub mod folder;
+pub mod interpreter;
+pub mod measured;
+pub mod parser;
+pub mod render;
+pub mod trace;
+pub mod ucum;
+pub mod unicode;
+pub mod units;
+pub mod register;
+pub mod rpc;
+pub mod script;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod server;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(test)]
+mod test_common {
+    use crate::ast::Expr;
+    use std::sync::{Mutex, MutexGuard};
+    pub use crate::register::init_units;
+
+    pub const EPS: f64 = 0.001;
+
+    // тесты, меняющие общий реестр (def, reload_vocabulary), идут по одному
+    static REGISTRY_WRITE: Mutex<()> = Mutex::new(());
+
+    pub fn registry_write() -> MutexGuard<'static, ()> {
+        REGISTRY_WRITE.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    #[allow(dead_code)]
+    pub fn test_conv_data<'a>() -> Vec<(u8, &'a str, f64)> {
+        vec![
+            (1, "1 Па^2=>Н^2/м^4", 1.0),
+            (2, "1 Па=>Н/м^2", 1.0),
+            (3, "1 к_Па=>Н/м^2", 1000.0),
+            (4, "1 к_Па^2=>Н^2/м^4", 1000000.0),
+            (5, "1 к_Па^2/сут^3=>кг^2/м^2*с^7", 1.55045359574252e-9),
+            (6, "1 к_Па^2/м_сут^3=>кг^2/м^2*с^7", 1.55045359574252),
+            (7, "1 к_Па^2/см^3=>кг^2/м^5*с^4", 1e12),
+            (7, "1 к_Па^2/с_м^3=>кг^2/м^5*с^4", 1e12),
+            (9, "1 к_Па^2/км^2=>кг^2/м^4*с^4", 1.0),
+            (9, "1 к_Па^2/к_м^2=>кг^2/м^4*с^4", 1.0),
+            (10, "1 ч^2=>с^2", 1.296e7),
+            (11, "1 сут^2=>с^2", 7.46496e9),
+            (12, "1 сут=>с", 86400.0),
+            (13, "1 мес30^2=>с^2", 6.94427904e12),
+            (14, "1 км/ч=>м/с", 0.2777777778),
+            (15, "1 сут^2/кгс^2=>с^6/кг^2*м^2", 77622233.2930381),
+            (16, "1 кг/м^3=>м_г/д_м^3", 1000.0),
+            (16, "1 кг/м^3=>м_г/дм^3", 1000.0),
+            (17, "1 атм/м^2=>Па/д_м^2", 1.01325e3),
+            (17, "1 атм/м^2=>Па/дм^2", 1.01325e3),
+            (18, "1 атм=>Па", 1.01325e5),
+            (19, "1 тс^3/В^2=>кг*А^2*м^-1", 9.431092984355795e11),
+            (20, "1 акр^2=>м^4", 16377075.8596),
+            (21, "1 кгс=>кг*м/с^2", 9.80665),
+            (22, "1 кгс^3=>кг^3*м^3/с^6", 943.1092984356),
+            (23, "1 сут^2/кгс^2=>с^6/кг^2*м^2", 77622233.2930381),
+            (24, "1 акр^2/сут^3=>м^4/с^3", 2.5391851259e-8),
+            (25, "1 акр^2/сут^2=>м^4/с^2", 2.1938559488e-3),
+            (26, "1 м*кг*м*кг*м*кг*м*м=>м^5*кг^3", 1.0),
+            (27, "1 кгс^2/кгс=>кгс", 1.0),
+            (28, "1 кгс^2/кгс^-1=>кгс^3", 1.0),
+            (29, "1 об/мин=>рад/с", 0.1047197551),
+            (30, "10 °=>рад", 0.1745329252),
+            (31, "5 %=>ppm", 50000.0),
+            (32, "1 ср/с=>ср/мин", 60.0),
+            (33, "10 дБм=>м_Вт", 10.0),
+            (34, "1 Вт=>дБм", 30.0),
+            (35, "20 дБ=>1", 100.0),
+            (36, "1 Нп=>дБ", 8.6858896381),
+            (37, "7 pH=>н_моль/дм^3", 100.0),
+        ]
+    }
+    #[allow(dead_code)]
+    pub fn test_deco_data<'a>() -> Vec<(u8, &'a str, &'a str)> {
+        vec![
+            (1, "Па/дм^2", "100.00000000 [кг^1 * м^-3 * с^-2]"),
+            (2, "кг/см^-2", "0.00010000 [м^2 * кг^1]"),
+            (3, "кг/с_м^-2", "0.00010000 [м^2 * кг^1]"),
+            (4, "к_г/с_м^-2", "0.00010000 [м^2 * кг^1]"),
+            (5, "к_г/см^-2", "0.00010000 [м^2 * кг^1]"),
+            (6, "кг/д_дм^-2", "0.00010000 [м^2 * кг^1]"),
+            (7, "к_г/д_дм^-2", "0.00010000 [м^2 * кг^1]"),
+            (8, "м_т/д_дм^-2", "0.00010000 [м^2 * кг^1]"),
+            (9, "м_т/см^-2", "0.00010000 [м^2 * кг^1]"),
+            (10, "м_т/с_м^-2", "0.00010000 [м^2 * кг^1]"),
+            (11, "кгс/с_м^2", "98066.50000000 [кг^1 * м^-1 * с^-2]"),
+            (12, "ат", "98066.50000000 [кг^1 * м^-1 * с^-2]"),
+            (13, "ppm", "0.00000100 [1]"),
+            (14, "°/с", "0.01745329 [рад^1 * с^-1]"),
+        ]
+    }
+
+    pub(crate) fn check_unit_parse(e: Expr, pfx: Option<String>, tag: String, pow: i8, den: bool) {
+        match e {
+            Expr::Unit {
+                pfx: p,
+                tag: t,
+                pow: w,
+                den: d,
+            } => {
+                assert_eq!(p, pfx);
+                assert_eq!(t, tag);
+                assert_eq!(w, pow);
+                assert_eq!(d, den);
+            }
+            _ => panic!("test failed"),
+        };
+    }
+
+    pub(crate) fn check_fraction_parse(e: Expr, up: Vec<Expr>, dn: Vec<Expr>) {
+        match e {
+            Expr::Fraction {
+                up: u_vec,
+                down: d_vec,
+            } => {
+                // numerator
+                for (i, u) in u_vec.into_iter().enumerate() {
+                    if let Expr::Unit {
+                        pfx: p,
+                        tag: t,
+                        pow: w,
+                        den: d,
+                    } = up.get(i).unwrap() { check_unit_parse(u, p.clone(), t.clone(), *w, *d) }
+                }
+                // denominator
+                for (j, u) in d_vec.into_iter().enumerate() {
+                    if let Expr::Unit {
+                        pfx: p,
+                        tag: t,
+                        pow: w,
+                        den: d,
+                    } = dn.get(j).unwrap() { check_unit_parse(u, p.clone(), t.clone(), *w, *d) }
+                }
+            }
+            _ => panic!("test failed"),
+        }
+    }
+
+    pub(crate) fn check_convert_parse(
+        e: Expr,
+        val: f64,
+        src_up: Vec<Expr>,
+        src_dn: Vec<Expr>,
+        dst_up: Vec<Expr>,
+        dst_dn: Vec<Expr>,
+    ) {
+        match e {
+            Expr::Convert(v, src_frac, dst_frac) => {
+                assert_eq!(v.v, val);
+                // source fraction
+                check_fraction_parse(*src_frac, src_up, src_dn);
+                // destination fraction
+                check_fraction_parse(*dst_frac, dst_up, dst_dn);
+            }
+            _ => panic!("test failed"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_parser_validation {
+    use crate::parser::*;
+
+    #[test]
+    fn test_enter_validation() {
+        // two spaces
+        assert!(enter_validation("1  кг=>т")
+            .is_err_and(|e| e.to_string().contains("0 or 1 is allowed")));
+
+        // wrong f32
+        assert!(enter_validation("f1 кг=>т")
+            .is_err_and(|e| e.to_string().contains("float input wrong")));
+
+        // wrong f32
+        assert!(enter_validation("1,0 кг=>т")
+            .is_err_and(|e| e.to_string().contains("float input wrong")));
+
+        // exactly 1 =>
+        assert!(enter_validation("10.1 кг")
+            .is_err_and(|e| e.to_string().contains("one '=>' occurrence")));
+
+        // exactly 1 =>
+        assert!(enter_validation("10.1 =>кг=>")
+            .is_err_and(|e| e.to_string().contains("one '=>' occurrence")));
+
+        // wrong uncertainty
+        assert!(enter_validation("12.5±x к_Па=>атм")
+            .is_err_and(|e| e.to_string().contains("uncertainty input wrong")));
+
+        // negative uncertainty
+        assert!(enter_validation("12.5±-0.2 к_Па=>атм")
+            .is_err_and(|e| e.to_string().contains("uncertainty input wrong")));
+    }
+
+    #[test]
+    fn test_parse_value() {
+        use crate::ast::Uncertainty;
+
+        let val = parse_value("12.5").unwrap();
+        assert_eq!((val.v, val.u), (12.5, None));
+
+        let val = parse_value("12.5±0.2").unwrap();
+        assert_eq!((val.v, val.u), (12.5, Some(Uncertainty::Abs(0.2))));
+
+        let val = parse_value("12.5±1.6%").unwrap();
+        assert_eq!((val.v, val.u), (12.5, Some(Uncertainty::Rel(0.016))));
+    }
+}
+
+#[cfg(test)]
+mod test_parser_unit {
+    use crate::parser::{parse_unit, ParseError};
+    use crate::test_common::check_unit_parse;
+
+    #[test]
+    fn test_parse_unit_nyn() {
+        // prefix N; tag Y; pow N;
+        let ast = parse_unit("кг", false).unwrap();
+        check_unit_parse(ast, None, "кг".to_string(), 1, false);
+    }
+
+    #[test]
+    fn test_parse_unit_yyn() {
+        // prefix Y; tag Y; pow N
+        let ast = parse_unit("мк_кг", false).unwrap();
+        check_unit_parse(ast, Some("мк".to_string()), "кг".to_string(), 1, false);
+    }
+
+    #[test]
+    fn test_parse_unit_nyy() {
+        // prefix Y; tag Y; pow N
+        let ast = parse_unit("кг^3", false).unwrap();
+        check_unit_parse(ast, None, "кг".to_string(), 3, false);
+    }
+
+    #[test]
+    fn test_parse_unit_yyy() {
+        // prefix Y; tag Y; pow Y
+        let ast = parse_unit("мк_кг^3", false).unwrap();
+        check_unit_parse(ast, Some("мк".to_string()), "кг".to_string(), 3, false);
+    }
+
+    #[test]
+    fn test_parse_unit_wrong_pow() {
+        for input in ["м^x", "м^", "м^1000", "к_м^x", "к_м^", "м^2^3", "к_к_м"] {
+            assert!(matches!(
+                parse_unit(input, false),
+                Err(ParseError::WrongPow(_) | ParseError::WrongUnit(_))
+            ), "{input}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_parser_expr {
+    use crate::ast::{Expr, Stmt};
+    use crate::parser::{parse_expr, parse_stmt, parse_unit};
+    use crate::test_common::{check_convert_parse, check_fraction_parse};
+
+    #[test]
+    fn test_parse_fraction_yn() {
+        // numerator Y; denominator N;
+        let ex = "м_г^3*см^2*к_с^-1";
+
+        let n1: Expr = parse_unit("м_г^3", false).unwrap();
+        let n2: Expr = parse_unit("см^2", false).unwrap();
+        let n3: Expr = parse_unit("к_с^-1", false).unwrap();
+
+        let up: Vec<Expr> = vec![n1, n2, n3];
+        let dn: Vec<Expr> = vec![];
+
+        let frac: Expr = parse_expr(ex).unwrap();
+        check_fraction_parse(frac, up, dn);
+    }
+
+    #[test]
+    fn test_parse_fraction_yy() {
+        // numerator Y; denominator Y;
+        let ex = "м_г^3*см^2*к_с^-1/Т_Гц^2*д_м^3";
+
+        let n1: Expr = parse_unit("м_г^3", false).unwrap();
+        let n2: Expr = parse_unit("см^2", false).unwrap();
+        let n3: Expr = parse_unit("к_с^-1", false).unwrap();
+
+        let d1: Expr = parse_unit("Т_Гц^2", true).unwrap();
+        let d2: Expr = parse_unit("д_м^3", true).unwrap();
+
+        let up: Vec<Expr> = vec![n1, n2, n3];
+        let dn: Vec<Expr> = vec![d1, d2];
+
+        let frac: Expr = parse_expr(ex).unwrap();
+        check_fraction_parse(frac, up, dn);
+    }
+
+    #[test]
+    fn test_parse_convert_yy_yn() {
+        // src: numerator Y; denominator Y,
+        // dst: numerator Y; denominator N;
+        let ex = "11.3 м_г^3*см^2*к_с^-1/Т_Гц^2*д_м^3=>н_Па^-2";
+
+        let vl: f64 = 11.3;
+
+        let sn1: Expr = parse_unit("м_г^3", false).unwrap();
+        let sn2: Expr = parse_unit("см^2", false).unwrap();
+        let sn3: Expr = parse_unit("к_с^-1", false).unwrap();
+
+        let sd1: Expr = parse_unit("Т_Гц^2", true).unwrap();
+        let sd2: Expr = parse_unit("д_м^3", true).unwrap();
+
+        let src_up: Vec<Expr> = vec![sn1, sn2, sn3];
+        let src_dn: Vec<Expr> = vec![sd1, sd2];
+
+        let dn1: Expr = parse_unit("н_Па^-2", false).unwrap();
+
+        let dst_up: Vec<Expr> = vec![dn1];
+        let dst_dn: Vec<Expr> = vec![];
+        let convert: Stmt = parse_stmt(ex).unwrap();
+
+        match convert {
+            Stmt::Conversation(conv) => {
+                check_convert_parse(conv, vl, src_up, src_dn, dst_up, dst_dn);
+            }
+            _ => panic!("test failed"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_parser_stmt {
+    use crate::ast::Stmt;
+    use crate::parser::parse_stmt;
+
+    #[test]
+    fn test_statement_dispatch() {
+        // if input contain =>  stmt will be Conversation
+        let ex = "11.3 м_г^3*см^2*к_с^-1/Т_Гц^2*д_м^3=>н_Па^-2";
+
+        let stmt: Stmt = parse_stmt(ex).unwrap();
+        match stmt {
+            Stmt::Conversation(_ast) => {}
+            _ => panic!("test failed"),
+        }
+
+        // else stmt will be Decomposition
+        let ex = "м_г^3*см^2*к_с^-1/Т_Гц^2*д_м^3*н_Па^-2";
+
+        let stmt: Stmt = parse_stmt(ex).unwrap();
+        match stmt {
+            Stmt::Decomposition(_ast) => {}
+            _ => panic!("test failed"),
+        }
+
+        // ...and simple unit will be Decomposition too
+        let ex = "н_Па^-2";
+
+        let stmt: Stmt = parse_stmt(ex).unwrap();
+        match stmt {
+            Stmt::Decomposition(_ast) => {}
+            _ => panic!("test failed"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_folder {
+    use crate::register::{load_vocabularies, units, Registry};
+    use crate::test_common::init_units;
+    use crate::units::{to_bases, BaseUnits, ParsedUnit, Unit};
+
+    fn log(h: &str, src: Unit, m: f64, v: Vec<Unit>) {
+        println!("1 {:?} = {:?} {:?}^{:?}", h, src.mpl, src.tag, src.pow);
+        println!("{:?} {:?}", m, v);
+    }
+
+
+    #[test]
+    fn test_to_bases_fn_synth() {
+        init_units();
+        let voc = units();
+
+        // A: 2 B^2
+        //    4 C^3
+        // B: 3 D^1
+        // C: 2 E^2
+        //    4 F^3
+        // D: 7 H^2
+        // E: 4 H^1
+        // F: 5 H^3
+        // H: []
+
+        let mut a: Unit = voc.get("A").unwrap().clone();
+        let ba = {
+            a.pow = 1;
+            a.mpl = 1.0;
+            a
+        };
+        let (m, v) = to_bases(&ba, &voc).unwrap();
+        log("A", ba, m, v.clone());
+
+        let mut a: Unit = voc.get("A").unwrap().clone();
+        let a2 = {
+            a.pow = 2;
+            a.mpl = 1.0;
+            a
+        };
+        let (m, v) = to_bases(&a2, &voc).unwrap();
+        log("A^2", a2, m, v.clone());
+
+        let mut a: Unit = voc.get("A").unwrap().clone();
+        let ka = {
+            a.pow = 1;
+            a.mpl = 1e3;
+            a
+        };
+        let (m, v) = to_bases(&ka, &voc).unwrap();
+        log("кA", ka, m, v.clone());
+
+        let mut a: Unit = voc.get("A").unwrap().clone();
+        let sa = {
+            a.pow = 1;
+            a.mpl = 1e-2;
+            a
+        };
+        let (m, v) = to_bases(&sa, &voc).unwrap();
+        log("sA", sa, m, v.clone());
+
+        let mut a: Unit = voc.get("A").unwrap().clone();
+        let ka2 = {
+            a.pow = 2;
+            a.mpl = 1e3;
+            a
+        };
+        let (m, v) = to_bases(&ka2, &voc).unwrap();
+        log("кA^2", ka2, m, v.clone());
+
+        let mut a: Unit = voc.get("A").unwrap().clone();
+        let sa2 = {
+            a.pow = 2;
+            a.mpl = 1e-2;
+            a
+        };
+        let (m, v) = to_bases(&sa2, &voc).unwrap();
+        log("sA^2", sa2, m, v.clone());
+        println!("========");
+    }
+
+    #[test]
+    fn test_to_bases_fn() {
+        init_units();
+        let voc = units();
+
+        // к_Н
+        let mut n: Unit = voc.get("Н").unwrap().clone();
+        let kn = {
+            n.pow = 1;
+            n.mpl = 1e3;
+            n
+        };
+        let (m, v) = to_bases(&kn, &voc).unwrap();
+        log("кН", kn, m, v.clone());
+
+        // к_Н^2
+        let mut n: Unit = voc.get("Н").unwrap().clone();
+        let kn2 = {
+            n.pow = 2;
+            n.mpl = 1e6_f64;
+            n
+        };
+        let (m, v) = to_bases(&kn2, &voc).unwrap();
+        log("кН^2", kn2, m, v.clone());
+
+        // д_Н^3
+        let mut n: Unit = voc.get("Н").unwrap().clone();
+        let dn3 = {
+            n.pow = 3;
+            n.mpl = 1e-3_f64;
+            n
+        };
+        let (m, v) = to_bases(&dn3, &voc).unwrap();
+        log("дН^3", dn3, m, v.clone());
+
+        // кгс
+        let mut n: Unit = voc.get("кгс").unwrap().clone();
+        let kgs = {
+            n.pow = 1;
+            n.mpl = 1.0;
+            n
+        };
+        let (m, v) = to_bases(&kgs, &voc).unwrap();
+        log("кгс", kgs, m, v.clone());
+        println!("========");
+    }
+
+    #[test]
+    fn test_registry_cache() {
+        // свой реестр: insert не должен менять общий
+        let mut voc = Registry::from_vocabularies(load_vocabularies()).unwrap();
+
+        let mut kgs: Unit = voc.get("кгс").unwrap().clone();
+        kgs.pow = 2;
+        kgs.mpl = 1e6;
+
+        let (m, v) = to_bases(&kgs, &voc).unwrap();
+        let reduced = voc.reduce_unit(&kgs).unwrap().clone();
+        assert!(voc.is_cached("кгс", 2));
+        assert!(!voc.is_cached("кгс", 3));
+
+        // в кэше множитель без приставки
+        assert!((reduced.mpl * kgs.mpl - m).abs() < 1e-6);
+        assert_eq!(reduced.units, v);
+        assert_eq!(voc.reduce_unit(&kgs).unwrap().mpl, reduced.mpl);
+
+        // изменение словаря сбрасывает кэш
+        let mut cached = kgs.clone();
+        cached.tag = "test_cached".to_string();
+        voc.insert(cached);
+        assert!(!voc.is_cached("кгс", 2));
+
+        init_units();
+        assert!(!units().contains_key("test_cached"));
+    }
+
+    #[test]
+    fn test_embedded_units() {
+        // вшитые словари (wasm) совпадают с файлами из ./voc
+        let disk = crate::register::load_units();
+        let embedded = crate::register::load_embedded_units();
+        assert_eq!(disk.len(), embedded.len());
+        for (tag, u) in &disk {
+            // Unit::eq сравнивает только базовые единицы, сверяем целиком
+            assert_eq!(format!("{u:?}"), format!("{:?}", embedded[tag]));
+        }
+    }
+
+    #[test]
+    fn test_vocabularies() {
+        use crate::register::{load_vocabulary_from_str, Registry, VocError};
+
+        // старый плоский формат - версия "0", имя по умолчанию
+        let flat = load_vocabulary_from_str("flat", r#"[{"tag": "x", "base": []}]"#).unwrap();
+        assert_eq!((flat.header.name.as_str(), flat.header.version.as_str()), ("flat", "0"));
+        assert_eq!(flat.units[0].voc.as_deref(), Some("flat@0"));
+
+        let doc = r#"{"name": "y_units", "version": "2.1", "depends": ["flat"],
+            "units": [{"tag": "y", "symbol": "Y", "deprecated": "old", "exact": false,
+                       "base": [{"tag": "x", "mpl": 2, "pow": 1}]}]}"#;
+        let ver = load_vocabulary_from_str("ignored", doc).unwrap();
+        assert_eq!(ver.header.depends, ["flat"]);
+        let y = &ver.units[0];
+        assert_eq!((y.meta.symbol.as_deref(), y.meta.deprecated.as_deref()), (Some("Y"), Some("old")));
+        assert!(!y.is_exact());
+
+        assert!(matches!(
+            Registry::from_vocabularies(vec![ver.clone()]),
+            Err(VocError::MissingDependency(v, d)) if v == "y_units" && d == "flat"
+        ));
+        let reg = Registry::from_vocabularies(vec![flat, ver]).unwrap();
+        assert_eq!(reg.vocabularies().len(), 2);
+
+        // метаданные словарей ./voc и источник результата
+        init_units();
+        {
+            let voc = units();
+            assert_eq!(voc["Па"].meta.symbol.as_deref(), Some("Pa"));
+            assert!(voc["кгс"].meta.deprecated.is_some());
+            assert!(voc["мм.рт.ст"].is_exact() && !voc["tta"].is_exact());
+        }
+        let mut ii = crate::interpreter::Interpreter::new();
+        ii.deco("кгс").unwrap();
+        assert_eq!(Vec::from_iter(ii.state.vocs.clone()), ["c_units@1.0.0", "d_units@1.0.0"]);
+        ii.conv_f64("1 акр=>к_м^2").unwrap();
+        assert_eq!(Vec::from_iter(ii.state.vocs), ["c_units@1.0.0", "test_units@0"]);
+    }
+
+    #[test]
+    fn test_vocabulary_formats() {
+        use crate::register::{load_vocabulary, load_vocabulary_from_file, VocFormat};
+        use std::path::Path;
+
+        let toml = r#"
+            name = "f_units"
+            version = "1.0.0"
+            depends = ["c_units"]
+
+            [[units]]
+            tag = "кгс"
+            kind = "force"
+            base = [{ tag = "Н", mpl = 9.80665, pow = 1 }]
+        "#;
+        let yaml = "
+            name: f_units
+            version: 1.0.0
+            depends: [c_units]
+            units:
+              - tag: кгс
+                kind: force
+                base:
+                  - {tag: Н, mpl: 9.80665, pow: 1}
+        ".replace("\n            ", "\n");
+        let text = "
+            # единица на строку
+            @name f_units
+            @version 1.0.0
+            @depends c_units
+            кгс = 9.80665 Н
+            дюйм = 2.54 с_м      # приставка переходит в множитель
+            Н2 = кг*м/с^2
+            х
+        ";
+
+        let toml = load_vocabulary("x", toml, VocFormat::Toml).unwrap();
+        let yaml = load_vocabulary("x", &yaml, VocFormat::Yaml).unwrap();
+        let text = load_vocabulary("x", text, VocFormat::Text).unwrap();
+
+        for voc in [&toml, &yaml, &text] {
+            assert_eq!(voc.header.name, "f_units");
+            assert_eq!(voc.header.depends, ["c_units"]);
+            let kgs = &voc.units[0];
+            assert_eq!((kgs.tag.as_str(), kgs.base[0].tag.as_str(), kgs.base[0].mpl), ("кгс", "Н", 9.80665));
+            assert_eq!(kgs.voc.as_deref(), Some("f_units@1.0.0"));
+        }
+        assert_eq!(toml.units[0].kind.as_deref(), Some("force"));
+        assert_eq!(yaml.units[0].kind.as_deref(), Some("force"));
+
+        assert!((text.units[1].base[0].mpl - 0.0254).abs() < 1e-12);
+        assert_eq!(text.units[2].base.len(), 3);
+        assert_eq!(text.units[2].base[2].pow, -2);
+        assert!(text.units[3].base.is_empty());
+
+        // плоский yaml - версия "0"
+        let flat = load_vocabulary("y", "- tag: y\n  base: []", VocFormat::Yaml).unwrap();
+        assert_eq!((flat.header.name.as_str(), flat.header.version.as_str()), ("y", "0"));
+        // плоский toml - [[units]] без заголовка
+        let flat = load_vocabulary("t", "[[units]]\ntag = \"t\"\nbase = []", VocFormat::Toml).unwrap();
+        assert_eq!((flat.header.name.as_str(), flat.header.version.as_str()), ("t", "0"));
+        assert_eq!(flat.units[0].voc.as_deref(), Some("t@0"));
+        // заголовок без версии - ошибка, как и в json
+        assert!(load_vocabulary("t", "name = \"t\"\n[[units]]\ntag = \"t\"", VocFormat::Toml).is_err());
+
+        let err = load_vocabulary("z", "а = 1\nб = 2 3 Н", VocFormat::Text).unwrap_err();
+        assert!(err.to_string().starts_with("line 2"), "{err}");
+
+        assert_eq!(VocFormat::from_path(Path::new("voc/x.yml")).unwrap(), VocFormat::Yaml);
+        assert!(VocFormat::from_path(Path::new("voc/x.xml")).is_err());
+
+        let path = std::env::temp_dir().join("fold_pattern_test_units.voc");
+        std::fs::write(&path, "кгс = 9.80665 Н").unwrap();
+        let file = load_vocabulary_from_file(path.clone()).unwrap();
+        assert_eq!(file.header.name, "fold_pattern_test_units");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_vocabulary_dsl_base() {
+        use crate::register::{load_vocabulary, load_vocabulary_from_str, VocFormat};
+
+        let doc = r#"[
+            {"tag": "кгс", "base": "9.80665 кг*м/с^2"},
+            {"tag": "л", "base": "д_м^3"},
+            {"tag": "бар", "base": [{"tag": "Па", "mpl": 100000, "pow": 1}]}
+        ]"#;
+        let voc = load_vocabulary_from_str("b", doc).unwrap();
+        let kgs = &voc.units[0].base;
+        assert_eq!(kgs.iter().map(|u| (u.tag.as_str(), u.pow)).collect::<Vec<_>>(),
+                   [("кг", 1), ("м", 1), ("с", -2)]);
+        assert_eq!(kgs[0].mpl, 9.80665);
+        assert!((voc.units[1].base[0].mpl - 1e-3).abs() < 1e-15);
+        assert_eq!(voc.units[2].base[0].tag, "Па");
+
+        let yaml = load_vocabulary("b", "- {tag: кгс, base: 9.80665 кг*м/с^2}", VocFormat::Yaml).unwrap();
+        assert_eq!(yaml.units[0].base.len(), 3);
+
+        for (base, err) in [
+            ("9.8±1% кг", "uncertainty"),
+            ("9.8 кг м", "spaces"),
+            ("x9.8 кг", "float"),
+            ("9.8 ы_кг", "prefix"),
+        ] {
+            let doc = format!(r#"[{{"tag": "x", "base": "{base}"}}]"#);
+            let e = load_vocabulary_from_str("b", &doc).unwrap_err();
+            assert!(e.to_string().contains(err), "{base}: {e}");
+        }
+    }
+
+    #[test]
+    fn test_add_parsed_unit_fn() {
+        // p1: den:Y, кН^2 -> mpl: 1/10^9 pow:-2
+        // p2: den:N, МН^3 -> mpl: 1/10^9 pow:-2
+        init_units();
+
+        let mut bu = BaseUnits::default();
+
+        let p1 = ParsedUnit {
+            pfx: Some("к".to_string()),
+            tag: "Н".to_string(),
+            pow: 2,
+            den: true,
+        };
+
+        let p2 = ParsedUnit {
+            pfx: Some("М".to_string()),
+            tag: "Н".to_string(),
+            pow: 3,
+            den: false,
+        };
+
+        let p3 = ParsedUnit {
+            pfx: Some("г".to_string()),
+            tag: "сут".to_string(),
+            pow: 2,
+            den: false,
+        };
+
+        let p1_mpl = 1.0 / 10f64.powi(3).powi(2i32); // к_Н^2
+        let p2_mpl = 10f64.powi(6).powi(3i32);       // М_Н^3
+        let p3_mpl = 10f64.powi(2).powi(2i32);       // г_сут^2
+
+        let _ = bu.add_parsed_unit(p1.clone());
+        assert_eq!(bu.units.get(&p1.tag).unwrap().mpl, p1_mpl);
+        assert_eq!(bu.units.get(&p1.tag).unwrap().pow, -p1.pow); // denominator
+
+        let _ = bu.add_parsed_unit(p2.clone());
+        assert_eq!(bu.units.get(&p1.tag).unwrap().mpl, p1_mpl * p2_mpl);
+        assert_eq!(bu.units.get(&p1.tag).unwrap().pow, -p1.pow + p2.pow);
+
+        let _ = bu.add_parsed_unit(p3.clone());
+        assert_eq!(bu.units.get(&p1.tag).unwrap().mpl, p1_mpl * p2_mpl);
+        assert_eq!(bu.units.get(&p1.tag).unwrap().pow, -p1.pow + p2.pow);
+
+        assert_eq!(bu.units.get(&p3.tag).unwrap().mpl, p3_mpl);
+        assert_eq!(bu.units.get(&p3.tag).unwrap().pow, p3.pow);
+
+        assert_eq!(bu.mpl, 1.0);
+    }
+}
+
+#[cfg(test)]
+mod test_script {
+    use crate::interpreter::Interpreter;
+    use crate::register::init_units;
+    use crate::script::Script;
+    use crate::test_common::registry_write;
+
+    #[test]
+    fn test_script_run() {
+        init_units();
+        let _write = registry_write();
+        let mut ii = Interpreter::new();
+
+        let src = "
+            # давление в системе
+            let p = 12±0.1 к_Па   # манометр
+            p=>атм
+            let q = p=>мм.рт.ст
+
+            1 км/ч=>м/с
+            def ммм = 1000 км
+            let r = 2 ммм
+            r=>км
+            q
+            1 м=>с
+            let к_г = 2 м
+        ";
+        let res = Script::new().run(&mut ii, src);
+
+        let lines: Vec<usize> = res.iter().map(|r| r.line).collect();
+        assert_eq!(lines, vec![3, 4, 5, 7, 8, 9, 10, 11, 12, 13]);
+
+        assert_eq!(res[0].out.as_ref().unwrap(), "p = 12±0.1 к_Па");
+        assert!(res[1].out.as_ref().unwrap().contains("=> 1.1843079200592153e-1±9.8692"));
+        assert!(res[2].out.as_ref().unwrap().starts_with("q = 9.0007"));
+        assert_eq!(res[6].out.as_ref().unwrap(), "2 ммм => 2e3 км");
+        assert!(res[7].out.as_ref().unwrap().ends_with(" мм.рт.ст"));
+        assert!(res[8].out.as_ref().is_err_and(|e| e.contains("not coherent")));
+        assert!(res[9].out.as_ref().is_err_and(|e| e.contains("binding must be")));
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod test_server {
+    use crate::interpreter::Interpreter;
+    use crate::register::init_units;
+    use crate::server::handle;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_server_handle() {
+        init_units();
+        let ii = Mutex::new(Interpreter::new());
+
+        let resp = handle(&ii, "POST", "/convert", r#"{"stmt": "1 атм=>к_Па"}"#);
+        assert_eq!(resp.status, 200);
+        assert!((resp.body["value"].as_f64().unwrap() - 101.325).abs() < 1e-9);
+
+        let resp = handle(&ii, "POST", "/decompose", r#"{"expr": "Па/дм^2"}"#);
+        assert_eq!(resp.status, 200);
+        assert_eq!(resp.body["units"]["м"], -3);
+        assert_eq!(resp.body["result"], "100.00000000 [кг^1 * м^-3 * с^-2]");
+
+        let resp = handle(&ii, "POST", "/compatible", r#"{"expr": "Па", "prefixed": true}"#);
+        assert_eq!(resp.status, 200);
+        assert!(resp.body.as_array().unwrap().iter().any(|c| c["tag"] == "к_Па" && c["factor"] == 1e-3));
+
+        let resp = handle(&ii, "GET", "/units", "");
+        assert!(resp.body.as_array().unwrap().contains(&"кгс".into()));
+
+        let resp = handle(&ii, "GET", "/prefixes", "");
+        assert_eq!(resp.body["к"], 3);
+
+        for (method, path, body, status, kind) in [
+            ("POST", "/convert", r#"{"stmt": "1  атм=>Па"}"#, 400, "TooMuchSpaces"),
+            ("POST", "/convert", r#"{"stmt": "1 м^x=>м"}"#, 400, "WrongPow"),
+            ("POST", "/decompose", r#"{"expr": "м^1000"}"#, 400, "WrongPow"),
+            ("POST", "/convert", r#"{"stmt": "1 атм=>с"}"#, 422, "NotCoherent"),
+            ("POST", "/decompose", r#"{"expr": "м^100*м^100"}"#, 422, "PowOverflow"),
+            ("POST", "/convert", r#"{"stmt": "1 м/м^-128=>м"}"#, 422, "PowOverflow"),
+            ("POST", "/convert", r#"{"stmt": "1 неизв=>с"}"#, 404, "NoUnit"),
+            ("POST", "/convert", r#"{"expr": "1 атм=>Па"}"#, 400, "BadRequest"),
+            ("POST", "/decompose", "{", 400, "BadRequest"),
+            ("GET", "/convert", "", 405, "MethodNotAllowed"),
+            ("GET", "/", "", 404, "NotFound"),
+        ] {
+            let resp = handle(&ii, method, path, body);
+            assert_eq!(resp.status, status, "{path} {body}");
+            assert_eq!(resp.body["error"]["kind"], kind, "{path} {body}");
+        }
+        // после ошибок сервис продолжает отвечать
+        assert_eq!(handle(&ii, "POST", "/convert", r#"{"stmt": "1 атм=>Па"}"#).status, 200);
+    }
+
+    #[test]
+    fn test_server_body_limit() {
+        use crate::server::handle_conn;
+        use std::io::{Read, Write};
+        use std::net::{TcpListener, TcpStream};
+
+        init_units();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let ii = Mutex::new(Interpreter::new());
+
+        let body = r#"{"stmt": "1 атм=>Па"}"#;
+        for (req, status) in [
+            ("POST /convert HTTP/1.1\r\nContent-Length: 1000000000000\r\n\r\n".to_owned(), "413"),
+            (format!("POST /convert HTTP/1.1\r\nContent-Length: {}\r\n\r\n{body}", body.len()), "200"),
+        ] {
+            let mut client = TcpStream::connect(addr).unwrap();
+            client.write_all(req.as_bytes()).unwrap();
+            let (conn, _) = listener.accept().unwrap();
+            handle_conn(conn, &ii).unwrap();
+
+            let mut resp = String::new();
+            client.read_to_string(&mut resp).unwrap();
+            assert!(resp.starts_with(&format!("HTTP/1.1 {status} ")), "{resp}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_rpc {
+    use crate::interpreter::Interpreter;
+    use crate::register::init_units;
+    use crate::rpc::run;
+    use serde_json::Value;
+
+    #[test]
+    fn test_rpc_run() {
+        init_units();
+        let mut ii = Interpreter::new();
+
+        let input = [
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "convert", "params": {"stmt": "1 атм=>к_Па"}}"#,
+            r#"{"jsonrpc": "2.0", "id": 2, "method": "decompose", "params": ["ат"]}"#,
+            r#"{"jsonrpc": "2.0", "id": 3, "method": "complete", "params": {"prefix": "мм"}}"#,
+            r#"{"jsonrpc": "2.0", "id": 4, "method": "validate", "params": {"stmt": "1 м=>с"}}"#,
+            r#"{"jsonrpc": "2.0", "method": "validate", "params": {"stmt": "1 м=>с"}}"#,
+            r#"{"jsonrpc": "2.0", "id": 5, "method": "convert", "params": {"stmt": "1 м=>с"}}"#,
+            r#"{"jsonrpc": "2.0", "id": 6, "method": "nope"}"#,
+            r#"{"jsonrpc": "2.0", "id": 7, "method": "convert", "params": {}}"#,
+            r#"{"jsonrpc": "2.0", "id": 8, "method": "convert", "params": {"stmt": "1 м^x=>м"}}"#,
+            r#"{"jsonrpc": "2.0", "id": 9, "method": "convert", "params": {"stmt": "1 атм=>Па"}}"#,
+            r#"{"jsonrpc"#,
+            r#"{"jsonrpc": "2.0", "id": 10, "method": "decompose", "params": ["м^100*м^100"]}"#,
+        ].join("\n");
+
+        let mut output = vec![];
+        run(input.as_bytes(), &mut output, &mut ii).unwrap();
+        let resp: Vec<Value> = String::from_utf8(output).unwrap().lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+
+        assert_eq!(resp.len(), 11);
+        assert!((resp[0]["result"]["value"].as_f64().unwrap() - 101.325).abs() < 1e-9);
+        assert_eq!(resp[1]["result"]["result"], "98066.50000000 [кг^1 * м^-1 * с^-2]");
+        assert_eq!(resp[2]["result"]["state"], "Unit");
+        let texts: Vec<&str> = resp[2]["result"]["candidates"].as_array().unwrap().iter()
+            .filter(|c| c["kind"] == "Unit")
+            .map(|c| c["text"].as_str().unwrap())
+            .collect();
+        assert_eq!(texts, ["мм", "мм.вод.ст", "мм.рт.ст"]);
+        assert_eq!(resp[3]["result"]["valid"], false);
+        assert_eq!(resp[3]["result"]["kind"], "NotCoherent");
+        assert_eq!(resp[4]["id"], 5);
+        assert_eq!(resp[4]["error"]["code"], -32002);
+        assert_eq!(resp[5]["error"]["code"], -32601);
+        assert_eq!(resp[6]["error"]["code"], -32602);
+        // ошибка разбора степени - объект ошибки, следующий запрос обслуживается
+        assert_eq!(resp[7]["error"]["code"], -32001);
+        assert_eq!(resp[7]["error"]["data"]["kind"], "WrongPow");
+        assert_eq!(resp[8]["result"]["value"], 101325.0);
+        assert_eq!(resp[9]["error"]["code"], -32700);
+        assert_eq!(resp[10]["error"]["data"]["kind"], "PowOverflow");
+    }
+
+    #[test]
+    fn test_rpc_error_codes() {
+        use crate::folder::UnitsError;
+        use crate::parser::ParseError;
+        use crate::rpc::{error_response, guarded};
+        use crate::ucum::UcumError;
+        use std::io;
+
+        let e = ParseError::TooMuchSpaces(2);
+        assert_eq!(error_response(1.into(), &e)["error"]["code"], -32001);
+        assert_eq!(error_response(1.into(), &e)["error"]["data"]["kind"], "TooMuchSpaces");
+        let e = UcumError::Value(ParseError::WrongPow("x".into()));
+        assert_eq!(error_response(1.into(), &e)["error"]["data"]["kind"], "WrongPow");
+        let e = UnitsError::NoUnit("x".into());
+        assert_eq!(error_response(1.into(), &e)["error"]["code"], -32002);
+        let e = io::Error::other("disk");
+        let resp = error_response(1.into(), &e);
+        assert_eq!(resp["error"]["code"], -32603);
+        assert_eq!(resp["error"]["data"]["kind"], "InternalError");
+
+        // паника в обработчике - ответ -32603 с id запроса
+        let resp = guarded(2.into(), || panic!("boom"));
+        assert_eq!(resp["id"], 2);
+        assert_eq!(resp["error"]["code"], -32603);
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod test_ffi {
+    use crate::ffi::*;
+    use std::ffi::{CStr, CString};
+    use std::ptr;
+
+    #[test]
+    fn test_ffi_roundtrip() {
+        unsafe {
+            let reg = uc_registry_new();
+            assert!(uc_last_error(reg).is_null());
+
+            let stmt = CString::new("1 к_Па=>Н/м^2").unwrap();
+            let mut v = 0.0;
+            assert_eq!(uc_convert(reg, stmt.as_ptr(), &mut v), UcStatus::Ok);
+            assert_eq!(v, 1000.0);
+
+            let expr = CString::new("ат").unwrap();
+            let (mut mpl, mut units) = (0.0, ptr::null_mut());
+            assert_eq!(uc_decompose(reg, expr.as_ptr(), &mut mpl, &mut units), UcStatus::Ok);
+            assert_eq!(mpl, 98066.5);
+            assert_eq!(CStr::from_ptr(units).to_str().unwrap(), "[кг^1 * м^-1 * с^-2]");
+            uc_free(units);
+
+            let stmt = CString::new("1 м=>с").unwrap();
+            assert_eq!(uc_convert(reg, stmt.as_ptr(), &mut v), UcStatus::NotCoherent);
+            assert!(!uc_last_error(reg).is_null());
+
+            // успешный вызов сбрасывает ошибку предыдущего
+            let stmt = CString::new("1 атм=>к_Па").unwrap();
+            assert_eq!(uc_convert(reg, stmt.as_ptr(), &mut v), UcStatus::Ok);
+            assert!(uc_last_error(reg).is_null());
+
+            let stmt = CString::new("1 ыы=>с").unwrap();
+            assert_eq!(uc_convert(reg, stmt.as_ptr(), &mut v), UcStatus::NoUnit);
+
+            let stmt = CString::new("1 м=>с=>с").unwrap();
+            assert_eq!(uc_convert(reg, stmt.as_ptr(), &mut v), UcStatus::ExactlyOneExprSeparator);
+
+            assert_eq!(uc_convert(reg, ptr::null(), &mut v), UcStatus::NullArg);
+            assert_eq!(uc_convert(ptr::null_mut(), stmt.as_ptr(), &mut v), UcStatus::NullArg);
+
+            uc_registry_free(reg);
+        }
+    }
+
+    #[test]
+    fn test_ffi_panic() {
+        unsafe {
+            let reg = uc_registry_new();
+            assert!(!reg.is_null());
+            assert_eq!(with_reg(reg, |_| panic!("boom")), UcStatus::Internal);
+            assert_eq!(CStr::from_ptr(uc_last_error(reg)).to_str().unwrap(), "internal error");
+
+            let stmt = CString::new("1 м^x=>м").unwrap();
+            let mut v = 0.0;
+            assert_eq!(uc_convert(reg, stmt.as_ptr(), &mut v), UcStatus::WrongPow);
+            uc_registry_free(reg);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_complete {
+    use crate::complete::{complete, CandidateKind, State};
+    use crate::register::init_units;
+
+    fn texts(input: &str) -> Vec<String> {
+        complete(input).candidates.into_iter().map(|c| c.text).collect()
+    }
+
+    #[test]
+    fn test_complete_states() {
+        init_units();
+
+        let c = complete("1 к_П");
+        assert_eq!(c.state, State::PrefixedUnit);
+        assert_eq!(c.token, "к_П");
+        assert!(texts("1 к_П").contains(&"к_Па".to_string()));
+        assert_eq!(c.apply("1 к_П", &c.candidates[0]), format!("1 {}", c.candidates[0].text));
+
+        // законченная единица: операторы, '/' уже был
+        let c = complete("кг/м^2*с");
+        assert_eq!(c.state, State::Unit);
+        assert!(c.candidates.iter().any(|c| c.text == "сут" && c.kind == CandidateKind::Unit));
+        assert!(c.candidates.iter().any(|c| c.text == "с_" && c.kind == CandidateKind::Prefix));
+        assert!(texts("кг/м^2*с").contains(&"^".to_string()));
+        assert!(!texts("кг/м^2*с").contains(&"/".to_string()));
+
+        // '=>' только в исходном выражении конвертации
+        assert!(texts("1 Па").contains(&"=>".to_string()));
+        assert!(!texts("1 Па=>атм").contains(&"=>".to_string()));
+
+        assert_eq!(complete("кг/м^").state, State::Pow);
+        assert!(texts("кг/м^").is_empty());
+        assert_eq!(texts("кг/м^2"), ["*"]);
+
+        assert_eq!(complete("1.5").state, State::Value);
+        assert_eq!(texts("1.5"), ["±", " "]);
+        assert_eq!(texts("1.5±0.1"), [" "]);
+
+        assert_eq!(complete("def x").state, State::DefTag);
+        assert_eq!(texts("def x "), ["="]);
+        assert_eq!(complete("def x = 2").state, State::Value);
+        assert_eq!(complete("def x = 2 к_").state, State::PrefixedUnit);
+
+        assert_eq!(complete("ы_м").state, State::Invalid);
+        assert_eq!(complete("1 м=>с с").state, State::Invalid);
+    }
+}
+
+#[cfg(test)]
+mod test_export {
+    use crate::export::{to_csv, to_markdown, to_ucum};
+    use crate::register::{init_units, units};
+
+    #[test]
+    fn test_export_formats() {
+        init_units();
+        let voc = units();
+
+        let csv = to_csv(&voc);
+        assert!(csv.starts_with("tag,factor,dimension\n"));
+        assert!(csv.contains("\nкгс,9.80665,м*кг*с^-2\n"));
+        assert!(csv.contains("\nа,100,м^2\n"));
+        assert!(csv.contains("\nм,1,м\n"));
+        assert!(!csv.contains("дБм"));
+
+        let md = to_markdown(&voc);
+        assert!(md.contains("## d_units@1.0.0"));
+        assert!(md.contains("| Па | Pa | паскаль | pressure | 1 | `кг*м^-1*с^-2` |  |"));
+        assert!(md.contains("не рекомендуется: внесистемная единица, используйте Н"));
+        assert!(md.contains("| дБм |"));
+
+        let ucum = to_ucum(&voc);
+        assert!(ucum.contains("\nkgf = 9.80665 m.kg.s-2 # кгс\n"));
+        assert!(ucum.contains("\nm # м, base\n"));
+        assert!(ucum.contains("\nmm[Hg] = 133.322387415 kg.m-1.s-2 # мм.рт.ст\n"));
+        assert!(ucum.contains("\nOhm = 1 m2.kg.s-3.A-2 # Ом\n"));
+        assert!(!ucum.contains("Ω"));
+    }
+}
+
+#[cfg(test)]
+mod test_ucum {
+    use crate::interpreter::Interpreter;
+    use crate::register::init_units;
+    use crate::test_common::EPS;
+    use crate::ucum::{parse_expr, render, UcumError};
+    use crate::units::BaseUnits;
+
+    fn show(b: &BaseUnits) -> String {
+        format!("{:.8} {}", b.mpl, b.as_readable())
+    }
+
+    #[test]
+    fn test_ucum_parse() {
+        init_units();
+        let mut ii = Interpreter::new();
+
+        for (code, deco) in [
+            ("kg.m/s2", "1.00000000 [м^1 * кг^1 * с^-2]"),
+            ("N", "1.00000000 [м^1 * кг^1 * с^-2]"),
+            ("kPa", "1000.00000000 [кг^1 * м^-1 * с^-2]"),
+            ("10*3.m", "1000.00000000 [м^1]"),
+            ("/s", "1.00000000 [с^-1]"),
+            ("kg/(m.s2)", "1.00000000 [кг^1 * м^-1 * с^-2]"),
+            ("m/s.s", "1.00000000 [м^1]"),
+            ("mg{dry}/dm3", "0.00100000 [кг^1 * м^-3]"),
+            ("100.cm", "1.00000000 [м^1]"),
+        ] {
+            let res = ii.ucum(code).unwrap_or_else(|e| panic!("{code}: {e}"));
+            assert_eq!(show(&res), deco, "{code}");
+        }
+
+        let v = ii.ucum("1 mm[Hg]=>Pa").unwrap();
+        assert!((v.v.v * v.mpl - 133.322).abs() < EPS);
+        let v = ii.ucum("760 mm[Hg]=>atm").unwrap();
+        assert!((v.v.v * v.mpl - 1.0).abs() < EPS);
+
+        for (code, err) in [
+            ("kg.xx", "UnknownAtom"),
+            ("kmin", "UnknownAtom"),
+            ("kg..m", "WrongSyntax"),
+            ("(kg.m", "WrongSyntax"),
+            ("12.m", "UnsupportedFactor"),
+            ("m999", "WrongExponent"),
+        ] {
+            let e = parse_expr(code).map(|_| ()).unwrap_err();
+            assert!(format!("{e:?}").starts_with(err), "{code}: {e:?}");
+        }
+        assert!(matches!(crate::ucum::parse_stmt("x kg=>g"), Err(UcumError::Value(_))));
+    }
+
+    #[test]
+    fn test_ucum_render() {
+        init_units();
+        let mut ii = Interpreter::new();
+
+        for (code, out) in [
+            ("kPa", "10*3.kg.m-1.s-2"),
+            ("J/s", "m2.kg.s-3"),
+            ("%", "10*-2"),
+            ("rad/s", "rad.s-1"),
+            ("mJ/cm2", "10*1.kg.s-2"),
+        ] {
+            assert_eq!(render(&ii.ucum(code).unwrap()).unwrap(), out, "{code}");
+
+            // обратный разбор дает те же множитель и единицы
+            let bu = ii.ucum(code).unwrap();
+            let back = ii.ucum(&render(&bu).unwrap()).unwrap();
+            assert!((back.mpl / bu.mpl - 1.0).abs() < 1e-12, "{code}");
+            assert_eq!(back.units, bu.units, "{code}");
+        }
+
+        // дробный множитель в UCUM не выразим
+        for code in ["mm[Hg]", "km/h"] {
+            let bu = ii.ucum(code).unwrap();
+            assert!(matches!(render(&bu), Err(UcumError::UnsupportedFactor(_))), "{code}");
+        }
+        ii.deco("A").unwrap();
+        assert!(matches!(render(&ii.state), Err(UcumError::UnsupportedFactor(_))));
+    }
+}
+
+#[cfg(test)]
+mod test_unicode {
+    use crate::interpreter::Interpreter;
+    use crate::register::init_units;
+    use crate::test_common::EPS;
+    use crate::unicode::{normalize, normalize_split, pretty};
+
+    #[test]
+    fn test_unicode_normalize() {
+        init_units();
+        for (src, res) in [
+            ("1 кг·м²=>Н·с²", "1 кг*м^2=>Н*с^2"),
+            ("1 м·с⁻¹=>км/ч", "1 м*с^-1=>км/ч"),
+            ("кг⋅м×с^²", "кг*м*с^2"),
+            ("1\u{a0}Па=>атм", "1 Па=>атм"),
+            ("\u{2009}1\u{202f}Па=>атм ", "1 Па=>атм"),
+            ("−1 Па=>атм", "-1 Па=>атм"),
+            // латинские k, c, P вместо кириллических
+            ("1 kг=>т", "1 кг=>т"),
+            ("1 к_Пa=>aтм", "1 к_Па=>атм"),
+            ("1 k_Па/c=>Па/с", "1 к_Па/с=>Па/с"),
+            // латинские теги словаря остаются латинскими
+            ("1 ppm=>%", "1 ppm=>%"),
+            // приставка без "_" не отделяется: кВт - неизвестный тег
+            ("1 кВт*ч=>МДж", "1 кВт*ч=>МДж"),
+            ("def кмоль = 1000 моль", "def кмоль = 1000 моль"),
+            // тег в def не меняется и при нескольких пробелах
+            ("def kг = 1 г", "def kг = 1 г"),
+            ("def  kг = 1 г", "def  kг = 1 г"),
+        ] {
+            assert_eq!(normalize(src), res, "{src}");
+        }
+        // по опции приставка отделяется, известные теги и тег в def не трогаются
+        assert_eq!(normalize_split("1 кВт*ч=>МДж"), "1 к_Вт*ч=>М_Дж");
+        assert_eq!(normalize_split("1 мкм=>см"), "1 мк_м=>см");
+        assert_eq!(normalize_split("def кмоль = 1 кмоль"), "def кмоль = 1 к_моль");
+
+        let mut ii = Interpreter::new();
+        let v = ii.conv_f64("1\u{a0}к_Па·с⁻¹=>Па/с").unwrap();
+        assert!((v - 1000.0).abs() < EPS);
+        // двойной пробел по-прежнему ошибка
+        assert!(ii.conv_f64("1\u{a0} Па=>атм").is_err());
+    }
+
+    #[test]
+    fn test_unicode_pretty() {
+        init_units();
+        assert_eq!(pretty("[кг^1 * м^-1 * с^-2]"), "[кг·м⁻¹·с⁻²]");
+        assert_eq!(pretty("1 км/ч^2=>м*с^-12"), "1 км/ч²=>м·с⁻¹²");
+
+        let mut ii = Interpreter::new();
+        ii.pretty = true;
+        assert_eq!(ii.deco("Па").unwrap(), "1.00000000 [кг·м⁻¹·с⁻²]");
+        assert_eq!(ii.conv("1 м²=>с_м²").unwrap(), "1 м² => 1e4 с_м²");
+    }
+}
+
+#[cfg(test)]
+mod test_render {
+    use crate::interpreter::Interpreter;
+    use crate::parser::{parse_expr, parse_stmt};
+    use crate::ast::Stmt;
+    use crate::register::init_units;
+    use crate::render::{latex, latex_expr, mathml, mathml_expr, siunitx, siunitx_expr};
+    use crate::units::{BaseUnits, Unit};
+
+    #[test]
+    fn test_render_expr() {
+        let e = parse_expr("кг*м^2/с^2").unwrap();
+        assert_eq!(latex_expr(&e), "\\mathrm{кг}\\cdot\\mathrm{м}^{2}\\,\\mathrm{с}^{-2}");
+        assert_eq!(siunitx_expr(&e), "\\si{кг.м^{2}.с^{-2}}");
+        assert_eq!(
+            mathml_expr(&e),
+            "<math xmlns=\"http://www.w3.org/1998/Math/MathML\"><mrow>\
+             <mi mathvariant=\"normal\">кг</mi><mo>⋅</mo>\
+             <msup><mi mathvariant=\"normal\">м</mi><mrow><mn>2</mn></mrow></msup><mo>⋅</mo>\
+             <msup><mi mathvariant=\"normal\">с</mi><mrow><mo>-</mo><mn>2</mn></mrow></msup>\
+             </mrow></math>"
+        );
+
+        let e = parse_expr("к_Па/мм.рт.ст").unwrap();
+        assert_eq!(latex_expr(&e), "\\mathrm{кПа}\\,\\mathrm{мм.рт.ст}^{-1}");
+        assert_eq!(siunitx_expr(&e), "\\si{кПа.\\text{мм.рт.ст}^{-1}}");
+        assert_eq!(latex_expr(&parse_expr("%").unwrap()), "\\mathrm{\\%}");
+
+        let Stmt::Conversation(conv) = parse_stmt("12.5±0.2 к_Па=>атм").unwrap() else { panic!() };
+        assert_eq!(latex_expr(&conv), "12.5\\pm0.2\\,\\mathrm{кПа}\\rightarrow\\mathrm{атм}");
+        assert_eq!(siunitx_expr(&conv), "\\SI{12.5+-0.2}{кПа}\\rightarrow\\si{атм}");
+
+        // безразмерное - \num
+        let Stmt::Conversation(conv) = parse_stmt("5 %=>1").unwrap() else { panic!() };
+        assert_eq!(siunitx_expr(&conv), "\\SI{5}{\\%}\\rightarrow\\num{1}");
+    }
+
+    #[test]
+    fn test_render_base_units() {
+        init_units();
+        let mut ii = Interpreter::new();
+
+        ii.deco("атм").unwrap();
+        assert_eq!(latex(&ii.state), "101325\\,\\mathrm{кг}\\,\\mathrm{м}^{-1}\\,\\mathrm{с}^{-2}");
+        assert_eq!(siunitx(&ii.state), "\\SI{101325}{кг.м^{-1}.с^{-2}}");
+
+        ii.deco("Н").unwrap();
+        assert_eq!(latex(&ii.state), "\\mathrm{м}\\cdot\\mathrm{кг}\\,\\mathrm{с}^{-2}");
+        assert!(mathml(&ii.state).starts_with("<math xmlns="));
+
+        ii.deco("к_м/м").unwrap();
+        assert_eq!(latex(&ii.state), "1000");
+        assert_eq!(siunitx(&ii.state), "\\num{1000}");
+        ii.state.mpl = 1.0;
+        assert_eq!(siunitx(&ii.state), "\\num{1}");
+
+        // множитель без шума двоичной арифметики
+        ii.deco("м").unwrap();
+        ii.state.mpl = 0.1 + 0.2;
+        assert_eq!(latex(&ii.state), "0.3\\,\\mathrm{м}");
+        assert_eq!(siunitx(&ii.state), "\\SI{0.3}{м}");
+        assert!(mathml(&ii.state).contains("<mn>0.3</mn>"));
+
+        // спецсимволы LaTeX в тегах
+        let mut bu = BaseUnits::new();
+        bu.units.insert("a".into(), Unit::new("x^y~z\\w", 1.0, 1));
+        assert_eq!(latex(&bu), "\\mathrm{x\\^{}y\\textasciitilde{}z\\textbackslash{}w}");
+    }
+}
+
+#[cfg(test)]
+mod test_canonical {
+    use crate::interpreter::Interpreter;
+    use crate::parser::ParseError;
+    use crate::register::init_units;
+    use crate::units::{self, canonical, log_data, Unit};
+
+    #[test]
+    fn test_canonical_order() {
+        let units = vec![
+            Unit::new("с", 1.0, -2),
+            Unit::new("моль", 1.0, 1),
+            Unit::new("кг", 1.0, 1),
+            Unit::new("рад", 1.0, 1),
+            Unit::new("м", 1.0, -1),
+            Unit::new("м", 1.0, 3),
+            Unit::new("А", 1.0, 1),
+            Unit::new("А", 1.0, -1),
+        ];
+        assert_eq!(log_data(&canonical(units)), "[м^2 * кг^1 * моль^1 * рад^1 * с^-2]");
+
+        init_units();
+        let mut ii = Interpreter::new();
+        for _ in 0..10 {
+            assert_eq!(ii.deco("В").unwrap(), "1.00000000 [м^2 * кг^1 * с^-3 * А^-1]");
+        }
+    }
+
+    #[test]
+    fn test_canonical_normalize() {
+        for (src, res) in [
+            ("с^-2*кг/м", "кг/м*с^2"),
+            ("кг/м*с^2", "кг/м*с^2"),
+            ("м*м/с", "м^2/с"),
+            ("м/м", "1"),
+            ("с^-1", "1/с"),
+            ("Па*к_м*м", "м*к_м*Па"),
+            ("А*моль^2*К/кд*м", "А*К*моль^2/м*кд"),
+        ] {
+            assert_eq!(units::normalize(src).unwrap(), res, "{src}");
+        }
+        // нормальная форма не зависит от порядка записи
+        assert_eq!(units::normalize("кг*м^2/с^2").unwrap(), units::normalize("с^-2*м^2*кг^-1*кг^2").unwrap());
+        assert!(units::normalize("кг*/с").is_err());
+        // сумма степеней за пределами i8 - ошибка, а не усечение
+        assert!(matches!(units::normalize("м^100*м^100"), Err(ParseError::WrongPow(p)) if p == "м^200"));
+        assert_eq!(units::normalize("м^100*м^27").unwrap(), "м^127");
+    }
+}
+
+#[cfg(test)]
+mod test_interpreter {
+    use crate::folder::{Folder, UnitsError};
+    use crate::interpreter::Interpreter;
+    use crate::parser::parse_stmt;
+    use crate::register::{init_units, units};
+    use crate::test_common::{registry_write, test_conv_data, test_deco_data, EPS};
+
+    #[test]
+    fn test_interpreter_deco() {
+        // декомпозиции
+        init_units();
+        let mut ii = Interpreter::new();
+
+        for (i, deco, expected) in test_deco_data().iter() {
+            match ii.deco(deco) {
+                Ok(v) => {
+                    print!("{i:5} ");
+                    assert_eq!(v, *expected);
+                    println!("DECO PASSED: {deco} = {v}");
+                }
+                Err(e) => panic!("{e}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_interpreter_conv() {
+        // конверсии
+        init_units();
+        let mut ii = Interpreter::new();
+
+        for (i, conv, ex_mpl) in test_conv_data().iter() {
+            match ii.conv_f64(conv) {
+                Ok(v) => {
+                    print!("{i:5} ");
+                    assert!((v - ex_mpl).abs() < EPS);
+                    println!("CONV PASSED: {conv} = {v}");
+                }
+                Err(e) => panic!("{e}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_interpreter_erase_pseudo() {
+        // Гц <=> рад/с разрешено только при стирании псевдо-размерностей
+        init_units();
+        let mut ii = Interpreter::new();
+
+        let stmt = parse_stmt("1 Гц=>рад/с").unwrap();
+        assert!(ii.fold_stmt(&stmt)
+            .is_err_and(|e| e.to_string().contains("not coherent")));
+
+        ii.opts.erase_pseudo = true;
+        let conv = ii.fold_stmt(&stmt).unwrap();
+        assert!((conv.v.v * conv.mpl - 1.0).abs() < EPS);
+        assert!(conv.units.contains_key("с"));
+        assert!(!conv.units.contains_key("рад"));
+    }
+
+    #[test]
+    fn test_interpreter_strict_kinds() {
+        // Дж и Н*м, Гц и Бк одной размерности, но разного рода
+        init_units();
+        let mut ii = Interpreter::new();
+
+        let refused = ["1 Дж=>Н*м", "1 Гц=>Бк", "1 к_Па=>Дж/м^3", "1 Вт*с=>Н*м"];
+        let allowed = [
+            "1 Дж=>кг*м^2/с^2", "1 кгс=>к_Н", "1 Н*м=>кгс*м", "1 км/ч=>м/с",
+            // род составного выражения выводится из DERIVED_KINDS
+            "1 к_Па=>Н/м^2", "1 Вт=>Дж/с", "1 Дж=>Вт*с", "1 к_Вт*ч=>М_Дж",
+        ];
+
+        for conv in refused.iter().chain(allowed.iter()) {
+            let stmt = parse_stmt(conv).unwrap();
+            assert!(ii.fold_stmt(&stmt).is_ok());
+        }
+
+        ii.opts.strict_kinds = true;
+        for conv in refused {
+            let stmt = parse_stmt(conv).unwrap();
+            assert!(ii.fold_stmt(&stmt)
+                .is_err_and(|e| e.to_string().contains("quantity kinds differ")));
+        }
+        for conv in allowed {
+            let stmt = parse_stmt(conv).unwrap();
+            assert!(ii.fold_stmt(&stmt).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_interpreter_uncertainty() {
+        init_units();
+        let mut ii = Interpreter::new();
+
+        // абсолютная и относительная неопределенность
+        let m = ii.conv_measured("12.5±0.2 к_Па=>Па").unwrap();
+        assert!((m.v - 12500.0).abs() < EPS && (m.u - 200.0).abs() < EPS);
+
+        let m = ii.conv_measured("12.5±2% к_Па=>Па").unwrap();
+        assert!((m.v - 12500.0).abs() < EPS && (m.u - 250.0).abs() < EPS);
+
+        // через логарифмическую шкалу: 10 мВт ± 1% => 10 дБм ± 0.0434
+        let m = ii.conv_measured("10±1% м_Вт=>дБм").unwrap();
+        assert!((m.v - 10.0).abs() < EPS && (m.u - 0.0434).abs() < EPS);
+
+        // неопределенность множителя учитывается только по запросу (tta = 5 tt ± 1%)
+        let m = ii.conv_measured("1 tta=>tt").unwrap();
+        assert_eq!(m.u, 0.0);
+
+        ii.opts.factor_unc = true;
+        let m = ii.conv_measured("1 tta=>tt").unwrap();
+        assert!((m.u - 5.0 * 0.01).abs() < 1e-9);
+        // мм.рт.ст задан полным значением 133.322387415 Па и точен
+        let m = ii.conv_measured("1 мм.рт.ст=>Па").unwrap();
+        assert!((m.v - 133.322387415).abs() < 1e-9 && m.u == 0.0);
+
+        let conv = ii.conv("1 tta^2=>tt^2").unwrap();
+        assert!(conv.contains("±"));
+
+        // точный вход и бесконечное значение - без ±NaN
+        assert_eq!(ii.conv("0 м_Вт=>дБм").unwrap(), "0 м_Вт => -inf дБм");
+        assert_eq!(ii.conv("1e400 м=>с_м").unwrap(), "1e400 м => inf с_м");
+        assert_eq!(ii.conv_measured("1 м_Вт=>дБм").unwrap().u, 0.0);
+    }
+
+    #[test]
+    fn test_measured_arithmetic() {
+        use crate::measured::Measured;
+
+        let a = Measured::new(10.0, 0.3);
+        let b = Measured::new(5.0, 0.4);
+
+        assert_eq!(a + b, Measured::new(15.0, 0.5));
+        assert_eq!(a - b, Measured::new(5.0, 0.5));
+        assert_eq!(a * 2.0, Measured::new(20.0, 0.6));
+        assert!(((a * b).rel() - 0.03f64.hypot(0.08)).abs() < 1e-12);
+        assert!(((a / b).rel() - 0.03f64.hypot(0.08)).abs() < 1e-12);
+
+        assert_eq!(Measured::exact(f64::INFINITY).with_rel(0.01).u, 0.0);
+        assert_eq!(a.with_rel(0.0), a);
+    }
+
+    #[test]
+    fn test_interpreter_compile() {
+        // скомпилированный конвертер совпадает с conv_f64
+        init_units();
+        let mut ii = Interpreter::new();
+
+        for (i, conv, ex_mpl) in test_conv_data().iter() {
+            let (v, stmt) = conv.split_once(' ').unwrap();
+            match ii.compile(stmt) {
+                Ok(c) => assert!((c.apply(v.parse().unwrap()) - ex_mpl).abs() < EPS),
+                Err(e) => assert!(e.to_string().contains("not linear"), "{i}: {e}"),
+            }
+        }
+
+        let c = ii.compile("к_Па=>атм").unwrap();
+        let src = [101.325, 202.65, 0.0];
+        let mut dst = [0.0; 3];
+        c.apply_slice(&src, &mut dst).unwrap();
+        assert!((dst[0] - 1.0).abs() < EPS && (dst[1] - 2.0).abs() < EPS);
+        assert!(c.apply_slice(&src, &mut [0.0; 2]).is_err());
+
+        let mut vals = src;
+        c.apply_in_place(&mut vals);
+        assert_eq!(vals, dst);
+        assert_eq!(c.apply_iter(src).collect::<Vec<_>>(), dst);
+
+        assert!(ii.compile("дБм=>Вт").is_err_and(|e| e.to_string().contains("not linear")));
+        assert!(ii.compile("к_Па=>атм=>Па").is_err());
+    }
+
+    #[test]
+    fn test_interpreter_try_conv() {
+        // conv_* печатают ошибку единиц и возвращают 0, try_conv_* возвращают ошибку
+        init_units();
+        let mut ii = Interpreter::new();
+
+        assert_eq!(ii.conv_f64("1 м=>с").unwrap(), 0.0);
+        assert!(ii.try_conv_f64("1 м=>с").is_err_and(|e| e.is::<UnitsError>()));
+        assert!(ii.try_conv("1 м=>с").is_err());
+        // ошибки разбора возвращаются в обоих случаях
+        assert!(ii.conv_f64("1 м^x=>с").is_err());
+        assert_eq!(ii.try_conv("1 атм=>к_Па").unwrap(), "1 атм => 1.01325e2 к_Па");
+    }
+
+    #[test]
+    fn test_interpreter_conversion_factor() {
+        init_units();
+        let mut ii = Interpreter::new();
+
+        let kpa_atm = ii.compile_factor("к_Па=>атм").unwrap();
+        assert_eq!((kpa_atm.src.as_str(), kpa_atm.dst.as_str()), ("к_Па", "атм"));
+        assert_eq!(kpa_atm.dims, [("кг".into(), 1), ("м".into(), -1), ("с".into(), -2)]);
+        assert!((kpa_atm.apply(101.325) - 1.0).abs() < 1e-12);
+
+        // обратный множитель
+        let atm_kpa = kpa_atm.inverse();
+        assert_eq!(atm_kpa.dst, "к_Па");
+        assert!((atm_kpa.apply(1.0) - 101.325).abs() < 1e-9);
+        assert!((kpa_atm.then(&atm_kpa).unwrap().factor - 1.0).abs() < 1e-12);
+
+        // композиция совпадает с прямой конвертацией
+        let atm_mmhg = ii.compile_factor("атм=>мм.рт.ст").unwrap();
+        let chain = kpa_atm.then(&atm_mmhg).unwrap();
+        let direct = ii.compile_factor("к_Па=>мм.рт.ст").unwrap();
+        assert_eq!((chain.src.as_str(), chain.dst.as_str()), ("к_Па", "мм.рт.ст"));
+        assert!((chain.factor / direct.factor - 1.0).abs() < 1e-12);
+
+        assert!((chain.apply(7.0) - atm_mmhg.apply(kpa_atm.apply(7.0))).abs() < 1e-9);
+
+        // промежуточные единицы разные, но одной размерности: атм и Па -
+        // then отказывает, then_rescaled пересчитывает
+        let pa_at = ii.compile_factor("Па=>ат").unwrap();
+        assert!(matches!(kpa_atm.then(&pa_at), Err(UnitsError::ChainMismatch(..))));
+        let chain = kpa_atm.then_rescaled(&pa_at).unwrap();
+        assert!((chain.apply(98.0665) - 1.0).abs() < 1e-12);
+        assert!((chain.inverse().apply(1.0) - 98.0665).abs() < 1e-9);
+
+        // нормальная форма: с^-2*кг/м и кг/м*с^2 - одна единица
+        let a = ii.compile_factor("атм=>с^-2*кг/м").unwrap();
+        let b = ii.compile_factor("кг/м*с^2=>к_Па").unwrap();
+        assert!((a.then(&b).unwrap().apply(1.0) - 101.325).abs() < 1e-9);
+
+        // размерности не совпадают
+        let km_m = ii.compile_factor("км/ч=>м/с").unwrap();
+        assert!(matches!(kpa_atm.then(&km_m), Err(UnitsError::NotCoherent(..))));
+        assert!(matches!(kpa_atm.then_rescaled(&km_m), Err(UnitsError::NotCoherent(..))));
+
+        assert!(ii.compile_factor("дБм=>Вт").is_err());
+        assert!(ii.compile_factor("м=>с").is_err());
+    }
+
+    #[test]
+    fn test_interpreter_rates() {
+        use crate::register::{reload_vocabulary, VocError};
+        use pathbuf::pathbuf;
+        use std::fs;
+
+        init_units();
+        // таблица меняет общий реестр: в конце он восстанавливается,
+        // def других тестов за это время не выполняются
+        let _write = registry_write();
+        let saved = units().clone();
+        let mut ii = Interpreter::new();
+        reload_vocabulary(pathbuf!["./voc", "rates.voc"]).unwrap();
+
+        // 1 руб/к_Вт*ч = 1/3.6 руб/М_Дж, USD = 92.5 руб
+        let v = ii.conv_f64("1 руб/к_Вт*ч=>USD/М_Дж").unwrap();
+        assert!((v - 1.0 / 3.6 / 92.5).abs() < 1e-12);
+        // пример из запроса как есть: приставки без "_" по опции
+        assert!(ii.try_conv_f64("1 руб/кВт*ч=>USD/МДж").is_err());
+        ii.split_prefixes = true;
+        assert!((ii.conv_f64("1 руб/кВт*ч=>USD/МДж").unwrap() - v).abs() < 1e-15);
+        ii.split_prefixes = false;
+        assert!((ii.conv_f64("1 EUR=>USD").unwrap() - 100.2 / 92.5).abs() < 1e-12);
+        assert!(ii.try_conv_f64("1 USD=>кг").is_err());
+
+        // перезагрузка: новые курсы, пропавшие из таблицы валюты удаляются
+        let path = std::env::temp_dir().join(format!("fold_pattern_rates_{}.voc", std::process::id()));
+        fs::write(&path, "@name rates\n@version 2026-10-20\nруб\nUSD = 80 руб\n").unwrap();
+        reload_vocabulary(path.clone()).unwrap();
+        assert!((ii.conv_f64("160 руб=>USD").unwrap() - 2.0).abs() < 1e-12);
+        assert!(ii.try_conv_f64("1 EUR=>руб").is_err());
+        assert!(units().vocabularies().iter().any(|h| h.name == "rates" && h.version == "2026-10-20"));
+
+        // единицы других словарей таблица не переопределяет
+        fs::write(&path, "@name rates\nруб\nм = 2 руб\n").unwrap();
+        let e = reload_vocabulary(path.clone()).unwrap_err();
+        assert!(matches!(e.downcast_ref::<VocError>(), Some(VocError::TagConflict(t, _)) if t == "м"));
+        assert!((ii.conv_f64("160 руб=>USD").unwrap() - 2.0).abs() < 1e-12);
+        fs::remove_file(path).unwrap();
+
+        *units() = saved;
+        assert!(!units().contains_key("руб"));
+    }
+
+    #[test]
+    fn test_interpreter_explain() {
+        // трасса дает тот же мультипликатор, что и свертка
+        init_units();
+        let mut ii = Interpreter::new();
+
+        for (i, conv, _) in test_conv_data().iter() {
+            let (res, trace) = ii.explain(conv).unwrap();
+            if res.log.is_none() {
+                assert!((trace.mpl / res.mpl - 1.0).abs() < 1e-9, "{i}: {conv}");
+            }
+        }
+        for (i, deco, _) in test_deco_data().iter() {
+            let (res, trace) = ii.explain(deco).unwrap();
+            assert!((trace.mpl / res.mpl - 1.0).abs() < 1e-9, "{i}: {deco}");
+        }
+
+        let (_, trace) = ii.explain("1 к_Па=>ат").unwrap();
+        let tree = trace.to_string();
+        assert!(tree.contains("prefix к = (10^3)^1"));
+        assert!(tree.contains("define кгс^1"));
+        assert!(tree.contains("factor 9.80665 of Н, ^1"));
+        assert!(tree.contains("factor 0.01 of м, ^-2 [x 10000]"));
+    }
+
+    #[test]
+    fn test_interpreter_define() {
+        init_units();
+        let _write = registry_write();
+        let mut ii = Interpreter::new();
+
+        let def = ii.define("def бар = 100000 Па").unwrap();
+        assert_eq!(def, "бар = 100000.00000000 [кг^1 * м^-1 * с^-2]");
+        ii.define("def узел = 1852 м/ч").unwrap();
+        ii.define("def мбар = 0.1±1% к_Па").unwrap();
+
+        assert!((ii.conv_f64("1 бар=>к_Па").unwrap() - 100.0).abs() < EPS);
+        assert!((ii.conv_f64("10 узел=>км/ч").unwrap() - 18.52).abs() < EPS);
+        assert!((ii.conv_f64("2 к_бар/с=>М_Па/с").unwrap() - 200.0).abs() < EPS);
+        ii.opts.factor_unc = true;
+        assert!((ii.conv_measured("1 мбар=>Па").unwrap().u - 1.0).abs() < EPS);
+
+        // род величины наследуется от выражения
+        ii.opts.strict_kinds = true;
+        assert!(ii.conv_f64("1 бар=>атм").is_ok());
+        assert!(ii.fold_stmt(&parse_stmt("1 бар=>Дж/м^3").unwrap()).is_err());
+
+        for (def, err) in [
+            ("def м = 100 см", "already exists"),
+            ("def бар = 1 Па", "already exists"),
+            ("def х = 2 х*м", "defined through itself"),
+            ("def ю = 2 неизв", "not found"),
+            ("def ю = 2 к_дБ", "standalone"),
+            ("def ю 2 м", "definition must be"),
+            ("def к_ю = 2 м", "definition must be"),
+            ("def ю = 2 м=>с", "definition must be"),
+            ("def ю = м м", "float input wrong"),
+        ] {
+            assert!(ii.define(def).is_err_and(|e| e.to_string().contains(err)), "{def}");
+        }
+    }
+
+    #[test]
+    fn test_interpreter_log_in_product() {
+        // логарифмические единицы - только сами по себе
+        init_units();
+        let mut ii = Interpreter::new();
+
+        for conv in ["1 дБм*с=>Дж", "1 Вт/дБм=>1", "1 дБм^2=>Вт^2", "1 к_дБ=>1"] {
+            let stmt = parse_stmt(conv).unwrap();
+            assert!(ii.fold_stmt(&stmt)
+                .is_err_and(|e| e.to_string().contains("standalone")));
+        }
+    }
+
+    #[test]
+    fn test_interpreter_compatible_units() {
+        init_units();
+        let mut ii = Interpreter::new();
+
+        let res = ii.compatible_units("Па", false).unwrap();
+        let factor = |tag: &str| res.iter().find(|c| c.tag == tag).map(|c| c.factor);
+
+        assert_eq!(factor("Па"), Some(1.0));
+        assert!((factor("атм").unwrap() - 1.0 / 101325.0).abs() < EPS);
+        assert!((factor("ат").unwrap() - 1.0 / 98066.5).abs() < EPS);
+        assert_eq!(factor("Дж"), None);
+        assert_eq!(factor("к_Па"), None);
+
+        let res = ii.compatible_units("к_Па", true).unwrap();
+        assert!(res.iter().any(|c| c.tag == "М_Па" && (c.factor - 1e-3).abs() < EPS));
+        assert!(res.iter().all(|c| c.tag != "к_атм"));
+
+        // приставки только к единицам СИ без приставки; для массы - к грамму
+        let mut tags = |expr: &str| -> Vec<String> {
+            ii.compatible_units(expr, true).unwrap().into_iter().map(|c| c.tag).collect()
+        };
+        let (length, time, mass) = (tags("м"), tags("с"), tags("кг"));
+        assert!(length.contains(&"к_м".into()) && length.contains(&"км".into()));
+        assert!(!length.contains(&"к_км".into()) && !length.contains(&"м_мм".into()));
+        assert!(time.contains(&"м_с".into()) && time.contains(&"ч".into()));
+        assert!(!time.contains(&"М_мин".into()) && !time.contains(&"к_ч".into()));
+        assert!(mass.contains(&"м_г".into()) && !mass.contains(&"к_кг".into()));
+
+        // логарифмические единицы нелинейны
+        assert!(ii.compatible_units("дБм", false).is_err());
+        assert!(ii.compatible_units("Вт", false).unwrap().iter().all(|c| c.tag != "дБм"));
+    }
+}
@@ -0,0 +1,117 @@
+// Measured value: v ± u, where u is absolute standard uncertainty.
+// Uncertainties are propagated to first order, inputs are uncorrelated.
+
+use crate::ast::{Uncertainty, Value};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::{Add, Div, Mul, Sub};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Measured {
+    pub v: f64,
+    pub u: f64,
+}
+
+impl Measured {
+    pub fn new(v: f64, u: f64) -> Measured {
+        // у бесконечного значения (переполнение, log 0) неопределенности нет, а не NaN
+        if !v.is_finite() {
+            return Self::exact(v);
+        }
+        Self { v, u: u.abs() }
+    }
+
+    pub fn exact(v: f64) -> Measured {
+        Self { v, u: 0.0 }
+    }
+
+    pub fn rel(&self) -> f64 {
+        if self.v == 0.0 {
+            return 0.0;
+        }
+        (self.u / self.v).abs()
+    }
+
+    pub fn with_rel(self, rel: f64) -> Measured {
+        // добавить относительную неопределенность (например множителя) в квадратуре
+        if rel == 0.0 {
+            return self;
+        }
+        Self::new(self.v, self.u.hypot(self.v * rel))
+    }
+}
+
+impl From<f64> for Measured {
+    fn from(v: f64) -> Self {
+        Self::exact(v)
+    }
+}
+
+impl From<Value> for Measured {
+    fn from(val: Value) -> Self {
+        match val.u {
+            Some(Uncertainty::Abs(u)) => Self::new(val.v, u),
+            Some(Uncertainty::Rel(r)) => Self::new(val.v, val.v * r),
+            None => Self::exact(val.v),
+        }
+    }
+}
+
+impl Mul<f64> for Measured {
+    type Output = Measured;
+
+    fn mul(self, k: f64) -> Measured {
+        Self::new(self.v * k, self.u * k)
+    }
+}
+
+impl Div<f64> for Measured {
+    type Output = Measured;
+
+    fn div(self, k: f64) -> Measured {
+        Self::new(self.v / k, self.u / k)
+    }
+}
+
+impl Add for Measured {
+    type Output = Measured;
+
+    fn add(self, o: Measured) -> Measured {
+        Self::new(self.v + o.v, self.u.hypot(o.u))
+    }
+}
+
+impl Sub for Measured {
+    type Output = Measured;
+
+    fn sub(self, o: Measured) -> Measured {
+        Self::new(self.v - o.v, self.u.hypot(o.u))
+    }
+}
+
+impl Mul for Measured {
+    type Output = Measured;
+
+    fn mul(self, o: Measured) -> Measured {
+        let v = self.v * o.v;
+        Self::new(v, (self.u * o.v).hypot(self.v * o.u))
+    }
+}
+
+impl Div for Measured {
+    type Output = Measured;
+
+    fn div(self, o: Measured) -> Measured {
+        let v = self.v / o.v;
+        Self::new(v, (self.u / o.v).hypot(v * o.u / o.v))
+    }
+}
+
+impl fmt::Display for Measured {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.u == 0.0 {
+            return write!(f, "{:e}", self.v);
+        }
+        write!(f, "{:e}±{:e}", self.v, self.u)
+    }
+}
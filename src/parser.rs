@@ -1,12 +1,17 @@
 use crate::ast::*;
+use crate::common::impl_error_kind;
 
 //@fmt:off
-const CONV: &str = "=>";
-const MUL: &str  = "*";
-const DIV: &str  = "/";
-const WS: &str   = " ";
-const US: &str   = "_";
-const PW: &str   = "^";
+pub(crate) const CONV: &str = "=>";
+pub(crate) const MUL: &str  = "*";
+pub(crate) const DIV: &str  = "/";
+pub(crate) const WS: &str   = " ";
+pub(crate) const US: &str   = "_";
+pub(crate) const PW: &str   = "^";
+pub(crate) const PM: &str   = "±";
+pub(crate) const PCT: &str  = "%";
+pub(crate) const DEF: &str  = "def";
+pub(crate) const EQ: &str   = "=";
 //@fmt:on
 
 #[derive(Debug, thiserror::Error)]
@@ -20,6 +25,9 @@ pub enum ParseError {
     #[error("float input wrong: {0}")]
     ValueWrongBegin(String),
 
+    #[error("uncertainty input wrong: {0}")]
+    WrongUncertainty(String),
+
     #[error("Convert Stmt allowed exactly one '=>' occurrence")]
     ExactlyOneExprSeparator(),
 
@@ -31,15 +39,28 @@ pub enum ParseError {
 
     #[error("pow cannot be parsed as valid i8: {0}")]
     WrongPow(String),
+
+    #[error("definition must be: def tag = float unit_expr, got: {0}")]
+    WrongDefinition(String),
 }
 
+impl_error_kind!(ParseError: TooMuchSpaces, WrongUnit, ValueWrongBegin, WrongUncertainty, ExactlyOneExprSeparator,
+    UnknownPrefix, UnknownUnit, WrongPow, WrongDefinition);
+
 pub fn enter_validation(input: &str) -> Result<&str, ParseError> {
     // So, about our little dsl grammar...
     // valid inputs are:
     //  - [float][WS][unit_expr][CONV][unit_expr]  ( with only one whitespace )
     //  - [unit_expr]
+    // float can carry uncertainty: [float][PM][float] or [float][PM][float][PCT]
     // unit_expr can contain one or several Unit, separated by many * and|or exactly one /
     // Unit is constrained as: [pfx][US][tag][PW][pow]
+    // and definition of new unit:
+    //  - [DEF][WS][tag][WS][EQ][WS][float][WS][unit_expr]
+
+    if input.starts_with(DEF) && input.contains(WS) {
+        return validate_def(input);
+    }
 
     // 0 or 1 space is allowed
     let ws: usize = input.matches(WS).count();
@@ -51,10 +72,7 @@ pub fn enter_validation(input: &str) -> Result<&str, ParseError> {
     if ws == 1 {
         let mut inp = input.splitn(2, WS);
         // try get float value
-        match inp.next().unwrap().parse::<f64>() {
-            Ok(_) => (),
-            Err(_) => return Err(ParseError::ValueWrongBegin(input.to_owned())),
-        };
+        parse_value(inp.next().unwrap())?;
         // exactly one '=>' allowed
         let conv = inp.next().unwrap().matches(CONV).count();
         match conv {
@@ -66,6 +84,53 @@ pub fn enter_validation(input: &str) -> Result<&str, ParseError> {
     Ok(input)
 }
 
+fn validate_def(input: &str) -> Result<&str, ParseError> {
+    let parts: Vec<&str> = input.split(WS).collect();
+    let reserved = [US, PW, MUL, DIV, EQ, PM];
+
+    match parts.as_slice() {
+        [DEF, tag, EQ, val, expr]
+        if !reserved.iter().any(|r| tag.contains(r))
+            && tag.parse::<f64>().is_err()
+            && !expr.contains(EQ) => {
+            parse_value(val)?;
+            Ok(input)
+        }
+        _ => Err(ParseError::WrongDefinition(input.to_owned())),
+    }
+}
+
+pub fn parse_value(input: &str) -> Result<Value, ParseError> {
+    // parse value as float with optional absolute or relative uncertainty
+
+    let (v, u) = match input.split_once(PM) {
+        Some((v, u)) => (v, Some(u)),
+        None => (input, None),
+    };
+
+    let v: f64 = match v.parse() {
+        Ok(v) => v,
+        Err(_) => return Err(ParseError::ValueWrongBegin(input.to_owned())),
+    };
+
+    let u = match u {
+        Some(u) => {
+            let (u, rel) = match u.strip_suffix(PCT) {
+                Some(u) => (u, true),
+                None => (u, false),
+            };
+            match u.parse::<f64>() {
+                Ok(u) if u >= 0.0 && rel => Some(Uncertainty::Rel(u / 100.0)),
+                Ok(u) if u >= 0.0 => Some(Uncertainty::Abs(u)),
+                _ => return Err(ParseError::WrongUncertainty(input.to_owned())),
+            }
+        }
+        None => None,
+    };
+
+    Ok(Value { v, u })
+}
+
 pub fn parse_unit(input: &str, den: bool) -> Result<Expr, ParseError> {
     // parse unit as pfx_tag^pow, where pfx is Option
     // den - is denominator flag
@@ -96,7 +161,7 @@ pub fn parse_unit(input: &str, den: bool) -> Result<Expr, ParseError> {
         tag = pt.next().unwrap().to_string();
 
         if let Some(s) = pt.next() {
-            pow = s.parse().unwrap();
+            pow = s.parse().map_err(|_| ParseError::WrongPow(input.to_owned()))?;
         }
     }
 
@@ -107,9 +172,14 @@ pub fn parse_unit(input: &str, den: bool) -> Result<Expr, ParseError> {
 
         let mut pt = tag_and_pow.rsplit(PW);
         if let Some(s) = pt.next() {
-            pow = s.parse().unwrap();
+            pow = s.parse().map_err(|_| ParseError::WrongPow(input.to_owned()))?;
         }
-        tag = pt.next().unwrap().to_string();
+        tag = pt.next().unwrap_or_default().to_string();
+    }
+
+    // несколько US или PW: м^2^3, к_к_м
+    if tag.is_empty() {
+        return Err(ParseError::WrongUnit(input.to_owned()));
     }
     Ok(Expr::Unit { pfx, tag, pow, den })
 }
@@ -141,10 +211,20 @@ pub fn parse_expr(input: &str) -> Result<Expr, ParseError> {
 }
 
 pub fn parse_stmt(input: &str) -> Result<Stmt, ParseError> {
+    // Definition statement
+    if input.starts_with(DEF) && input.contains(WS) {
+        let parts: Vec<&str> = input.split(WS).collect(); // already validated
+        return Ok(Stmt::Definition {
+            tag: parts[1].to_owned(),
+            val: parse_value(parts[3])?,
+            expr: parse_expr(parts[4])?,
+        });
+    }
+
     // Conversation statement
     if input.contains(CONV) {
         let mut inp = input.split(WS);
-        let val: f64 = inp.next().unwrap().parse().unwrap(); // already validated
+        let val = parse_value(inp.next().unwrap())?;
 
         let (lft, rht) = inp.next().unwrap().split_once(CONV).unwrap();
 
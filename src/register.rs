@@ -1,23 +1,209 @@
-use crate::units::Unit;
+use crate::folder::UnitsError;
+use crate::measured::Measured;
+use crate::units::{base_from_dsl, rel_uncertainty, source_vocabularies, to_bases, Unit};
 use once_cell::sync::OnceCell;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Deref;
 use std::sync::{Mutex, MutexGuard};
 use pathbuf::pathbuf;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::error::Error;
+use std::fmt;
 use std::fs;
-pub static UNITS: OnceCell<Mutex<HashMap<String, Unit>>> = OnceCell::new();
+pub static UNITS: OnceCell<Mutex<Registry>> = OnceCell::new();
+
+#[derive(Debug, Clone)]
+pub struct Reduced {
+    // единица без приставки tag^pow в базовых единицах
+    pub mpl: f64,
+    pub unc: f64,
+    pub units: Vec<Unit>,
+    // словари "name@version" цепочки определения
+    pub vocs: BTreeSet<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum VocError {
+    #[error("vocabulary {0} depends on {1}, which is not loaded")]
+    MissingDependency(String, String),
+
+    #[error("unknown vocabulary format: {0}")]
+    UnknownFormat(String),
+
+    #[error("line {0}: {1}")]
+    WrongLine(usize, String),
+
+    #[error("unit {0} is already defined by vocabulary {1}")]
+    TagConflict(String, String),
+}
+
+// формат словаря по расширению файла
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VocFormat {
+    Json,
+    Toml,
+    Yaml,
+    // по единице на строку: кгс = 9.80665 Н
+    Text,
+}
+
+impl VocFormat {
+    pub fn from_path(path: &Path) -> Result<VocFormat, VocError> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Ok(VocFormat::Json),
+            Some("toml") => Ok(VocFormat::Toml),
+            Some("yaml" | "yml") => Ok(VocFormat::Yaml),
+            Some("voc" | "txt") => Ok(VocFormat::Text),
+            _ => Err(VocError::UnknownFormat(path.to_string_lossy().into_owned())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VocHeader {
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub depends: Vec<String>,
+}
+
+// словарь: {"name", "version", "depends", "units": [...]}
+// или плоский массив единиц (старый формат, версия "0"; в toml - [[units]] без name)
+#[derive(Debug, Clone, Deserialize)]
+pub struct Vocabulary {
+    #[serde(flatten)]
+    pub header: VocHeader,
+    pub units: Vec<Unit>,
+}
+
+pub const UNVERSIONED: &str = "0";
+
+impl Vocabulary {
+    fn flat(name: &str, units: Vec<Unit>) -> Vocabulary {
+        let header = VocHeader { name: name.to_owned(), version: UNVERSIONED.to_owned(), depends: vec![] };
+        Self { header, units }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Registry {
+    units: HashMap<String, Unit>,
+    vocabularies: Vec<VocHeader>,
+    // tag -> pow -> Reduced, сбрасывается при любом изменении units
+    cache: HashMap<String, HashMap<i8, Reduced>>,
+}
+
+impl Deref for Registry {
+    type Target = HashMap<String, Unit>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.units
+    }
+}
+
+impl Registry {
+    pub fn new(units: HashMap<String, Unit>) -> Registry {
+        Self { units, vocabularies: vec![], cache: HashMap::new() }
+    }
+
+    pub fn from_vocabularies(vocs: Vec<Vocabulary>) -> Result<Registry, VocError> {
+        // зависимости должны присутствовать в наборе
+        let names: Vec<&str> = vocs.iter().map(|v| v.header.name.as_str()).collect();
+        for v in &vocs {
+            if let Some(dep) = v.header.depends.iter().find(|d| !names.contains(&d.as_str())) {
+                return Err(VocError::MissingDependency(v.header.name.clone(), dep.clone()));
+            }
+        }
+
+        let headers = vocs.iter().map(|v| v.header.clone()).collect();
+        let mut reg = Registry::new(collect_units(vocs.into_iter().map(|v| v.units)));
+        reg.vocabularies = headers;
+        Ok(reg)
+    }
+
+    pub fn vocabularies(&self) -> &[VocHeader] {
+        &self.vocabularies
+    }
+
+    /// Добавляет словарь или заменяет ранее загруженный с тем же именем:
+    /// таблицы, обновляемые во время работы (курсы валют, voc/rates.voc)
+    pub fn replace_vocabulary(&mut self, voc: Vocabulary) -> Result<(), VocError> {
+        let name = &voc.header.name;
+        let own = |u: &Unit| u.voc.as_deref().and_then(|v| v.split('@').next()) == Some(name.as_str());
+
+        if let Some(dep) = voc.header.depends.iter().find(|d| !self.vocabularies.iter().any(|h| h.name == **d)) {
+            return Err(VocError::MissingDependency(name.clone(), dep.clone()));
+        }
+        // единицы других словарей не переопределяются
+        for u in &voc.units {
+            if let Some(old) = self.units.get(&u.tag).filter(|old| !own(old)) {
+                let source = old.voc.clone().unwrap_or("runtime".into());
+                return Err(VocError::TagConflict(u.tag.clone(), source));
+            }
+        }
+
+        self.cache.clear();
+        self.units.retain(|_, u| !own(u));
+        self.vocabularies.retain(|h| h.name != *name);
+        self.vocabularies.push(voc.header);
+        for u in voc.units {
+            self.units.insert(u.tag.clone(), u);
+        }
+        Ok(())
+    }
+
+    pub fn insert(&mut self, u: Unit) {
+        self.cache.clear();
+        self.units.insert(u.tag.clone(), u);
+    }
+
+    pub fn is_cached(&self, tag: &str, pow: i8) -> bool {
+        self.cache.get(tag).is_some_and(|c| c.contains_key(&pow))
+    }
+
+    pub fn reduce_unit(&mut self, u: &Unit) -> Result<&Reduced, UnitsError> {
+        // memoized to_bases: мультипликатор приставки u.mpl сюда не входит
+        if !self.is_cached(&u.tag, u.pow) {
+            let mut proto = u.clone();
+            proto.mpl = 1.0;
+
+            let (mpl, units) = to_bases(&proto, &self.units)?;
+            let unc = u.pow as f64 * rel_uncertainty(&u.tag, &self.units);
+            let mut vocs = BTreeSet::new();
+            source_vocabularies(&u.tag, &self.units, &mut vocs);
+
+            self.cache
+                .entry(u.tag.clone())
+                .or_default()
+                .insert(u.pow, Reduced { mpl, unc, units, vocs });
+        }
+        Ok(&self.cache[&u.tag][&u.pow])
+    }
+}
 
 pub fn init_units() {
-    let units: HashMap<String, Unit> = load_units();
-    UNITS.get_or_init(|| Mutex::new(units));
+    UNITS.get_or_init(|| Mutex::new(Registry::from_vocabularies(load_vocabularies()).unwrap()));
+}
+
+pub fn init_embedded_units() {
+    // словари, вшитые при компиляции: не зависит от текущего каталога (FFI, wasm)
+    UNITS.get_or_init(|| Mutex::new(Registry::from_vocabularies(embedded_vocabularies()).unwrap()));
 }
 
 pub fn add_unit(u: Unit) {
-    UNITS.get().unwrap().lock().unwrap().insert(u.tag.clone(), u);
+    units().insert(u);
+}
+
+/// Загружает или перезагружает словарь из файла в общий реестр
+pub fn reload_vocabulary(path: PathBuf) -> Result<(), Box<dyn Error>> {
+    let voc = load_vocabulary_from_file(path)?;
+    units().replace_vocabulary(voc)?;
+    Ok(())
 }
 
-pub fn units() -> MutexGuard<'static, HashMap<String, Unit>> {
+pub fn units() -> MutexGuard<'static, Registry> {
     UNITS.get().unwrap().lock().unwrap()
 }
 
@@ -40,40 +226,154 @@ pub fn prefixes() -> HashMap<&'static str, i32> {
 }
 //@fmt:on
 
-pub fn load_units() -> HashMap<String, Unit> {
-    let c_units = pathbuf!["./voc/c_units.json"];
-    let cu = load_units_from_file(c_units).unwrap();
+// порядок важен: более поздние словари переопределяют одноимённые единицы
+pub const VOC_FILES: [&str; 7] = [
+    "c_units.json",
+    "d_units.json",
+    "u_units.json",
+    "test_ABC_units.json",
+    "test_units.json",
+    "a_units.json",
+    "l_units.json",
+];
 
-    let d_units = pathbuf!["./voc/d_units.json"];
-    let du = load_units_from_file(d_units).unwrap();
+// те же словари, вшитые в бинарник на этапе компиляции (wasm, без файловой системы)
+const EMBEDDED_VOC: [&str; 7] = [
+    include_str!("../voc/c_units.json"),
+    include_str!("../voc/d_units.json"),
+    include_str!("../voc/u_units.json"),
+    include_str!("../voc/test_ABC_units.json"),
+    include_str!("../voc/test_units.json"),
+    include_str!("../voc/a_units.json"),
+    include_str!("../voc/l_units.json"),
+];
 
-    let u_units = pathbuf!["./voc/u_units.json"];
-    let uu = load_units_from_file(u_units).unwrap();
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_vocabularies() -> Vec<Vocabulary> {
+    VOC_FILES
+        .iter()
+        .map(|f| load_vocabulary_from_file(pathbuf!["./voc", *f]).unwrap())
+        .collect()
+}
 
-    let a_units = pathbuf!["./voc/test_ABC_units.json"];
-    let au = load_units_from_file(a_units).unwrap();
+#[cfg(target_arch = "wasm32")]
+pub fn load_vocabularies() -> Vec<Vocabulary> {
+    embedded_vocabularies()
+}
+
+pub fn embedded_vocabularies() -> Vec<Vocabulary> {
+    VOC_FILES
+        .iter()
+        .zip(EMBEDDED_VOC)
+        .map(|(f, src)| load_vocabulary_from_str(voc_name(f), src).unwrap())
+        .collect()
+}
 
-    let t_units = pathbuf!["./voc/test_units.json"];
-    let tu = load_units_from_file(t_units).unwrap();
+pub fn load_units() -> HashMap<String, Unit> {
+    collect_units(load_vocabularies().into_iter().map(|v| v.units))
+}
 
-    let all_units = vec![cu, du, uu, au, tu]
-        .into_iter()
-        .flatten()
-        .collect::<Vec<Unit>>();
+pub fn load_embedded_units() -> HashMap<String, Unit> {
+    collect_units(embedded_vocabularies().into_iter().map(|v| v.units))
+}
 
+fn collect_units(all_units: impl Iterator<Item = Vec<Unit>>) -> HashMap<String, Unit> {
     let mut units = HashMap::new();
 
-    for unit in all_units {
+    for unit in all_units.flatten() {
         units.insert(unit.tag.clone(), unit);
     }
     units
 }
 
-pub fn load_units_from_file(path: PathBuf) -> Result<Vec<Unit>, Box<dyn Error>> {
-    let units = {
-        let res = fs::read_to_string(path).expect("Can't read file");
-        serde_json::from_str(&res)?
+fn voc_name(file: &str) -> &str {
+    file.rsplit('/').next().unwrap_or(file).split('.').next().unwrap_or(file)
+}
+
+pub fn load_vocabulary_from_file(path: PathBuf) -> Result<Vocabulary, Box<dyn Error>> {
+    let format = VocFormat::from_path(&path)?;
+    let res = fs::read_to_string(&path)?;
+    load_vocabulary(voc_name(&path.to_string_lossy()), &res, format)
+}
+
+pub fn load_vocabulary_from_str(name: &str, src: &str) -> Result<Vocabulary, Box<dyn Error>> {
+    load_vocabulary(name, src, VocFormat::Json)
+}
+
+pub fn load_vocabulary(name: &str, src: &str, format: VocFormat) -> Result<Vocabulary, Box<dyn Error>> {
+    // name - имя по умолчанию для словаря без заголовка
+    let mut voc = match format {
+        VocFormat::Json => match serde_json::from_str(src)? {
+            doc @ Value::Array(_) => Vocabulary::flat(name, serde_json::from_value(doc)?),
+            doc => serde_json::from_value(doc)?,
+        },
+        VocFormat::Yaml => match serde_yaml::from_str(src)? {
+            doc @ serde_yaml::Value::Sequence(_) => Vocabulary::flat(name, serde_yaml::from_value(doc)?),
+            doc => serde_yaml::from_value(doc)?,
+        },
+        // в toml нет массива верхнего уровня: плоский словарь - только [[units]]
+        VocFormat::Toml => match toml::from_str::<toml::Table>(src)? {
+            mut doc if !doc.contains_key("name") => {
+                let units = doc.remove("units").unwrap_or(toml::Value::Array(vec![]));
+                Vocabulary::flat(name, units.try_into()?)
+            }
+            doc => doc.try_into()?,
+        },
+        VocFormat::Text => parse_text_vocabulary(name, src)?,
     };
 
-    Ok(units)
-}
\ No newline at end of file
+    let source = format!("{}@{}", voc.header.name, voc.header.version);
+    for u in &mut voc.units {
+        u.voc = Some(source.clone());
+    }
+    Ok(voc)
+}
+
+fn parse_text_vocabulary(name: &str, src: &str) -> Result<Vocabulary, VocError> {
+    // # комментарий
+    // @name d_units / @version 1.0.0 / @depends c_units, u_units
+    // м                  - базовая единица
+    // кгс = 9.80665 Н    - tag = [float] unit_expr
+    let mut voc = Vocabulary::flat(name, vec![]);
+
+    for (n, line) in src.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let wrong = |e: &dyn fmt::Display| VocError::WrongLine(n + 1, format!("{line}: {e}"));
+
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(directive) = line.strip_prefix('@') {
+            let (key, val) = directive.split_once(' ').unwrap_or((directive, ""));
+            let val = val.trim().to_owned();
+            match key {
+                "name" => voc.header.name = val,
+                "version" => voc.header.version = val,
+                "depends" => voc.header.depends = val.split(',').map(|d| d.trim().to_owned()).collect(),
+                _ => return Err(wrong(&"unknown directive")),
+            }
+            continue;
+        }
+
+        let Some((tag, def)) = line.split_once('=') else {
+            voc.units.push(Unit::new(line, 1.0, 1));
+            continue;
+        };
+        let (tag, def) = (tag.trim(), def.trim());
+
+        let (val, base) = base_from_dsl(def).map_err(|e| wrong(&*e))?;
+        let mut unit = Unit::new(tag, 1.0, 1);
+        unit.base = base;
+        unit.unc = Measured::from(val).rel();
+        voc.units.push(unit);
+    }
+    Ok(voc)
+}
+
+pub fn load_units_from_file(path: PathBuf) -> Result<Vec<Unit>, Box<dyn Error>> {
+    Ok(load_vocabulary_from_file(path)?.units)
+}
+
+pub fn load_units_from_str(src: &str) -> Result<Vec<Unit>, Box<dyn Error>> {
+    Ok(load_vocabulary_from_str("", src)?.units)
+}
@@ -0,0 +1,240 @@
+// Render: выражения и BaseUnits для отчётов - LaTeX, siunitx и MathML
+//
+// кг*м^2/с^2     -> \mathrm{кг}\cdot\mathrm{м}^{2}\,\mathrm{с}^{-2}
+//                -> \si{кг.м^{2}.с^{-2}}
+// 1 к_Па=>атм    -> 1\,\mathrm{кПа}\rightarrow\mathrm{атм}
+// BaseUnits(атм) -> 101325\,\mathrm{кг}\,\mathrm{м}^{-1}\,\mathrm{с}^{-2}
+
+use crate::ast::{Expr, Uncertainty, Value};
+use crate::units::{BaseUnits, UNITY};
+
+const MATHML_NS: &str = "http://www.w3.org/1998/Math/MathML";
+
+// компонент выражения: приставка, тег, степень со знаком
+struct Factor {
+    pfx: Option<String>,
+    tag: String,
+    pow: i32,
+}
+
+impl Factor {
+    fn symbol(&self) -> String {
+        format!("{}{}", self.pfx.as_deref().unwrap_or_default(), self.tag)
+    }
+}
+
+fn expr_factors(expr: &Expr, out: &mut Vec<Factor>) {
+    // знаменатель отмечен флагом den самой единицы; безразмерная "1" множителем не считается
+    match expr {
+        Expr::Unit { pfx: None, tag, .. } if tag == UNITY => (),
+        Expr::Unit { pfx, tag, pow, den } => {
+            let pow = if *den { -(*pow as i32) } else { *pow as i32 };
+            out.push(Factor { pfx: pfx.clone(), tag: tag.clone(), pow });
+        }
+        Expr::Fraction { up, down } => {
+            up.iter().chain(down).for_each(|e| expr_factors(e, out));
+        }
+        Expr::Convert(..) => (),
+    }
+}
+
+fn base_factors(bu: &BaseUnits) -> Vec<Factor> {
+    bu.canonical().into_iter().map(|u| Factor { pfx: None, tag: u.tag, pow: u.pow as i32 }).collect()
+}
+
+fn num(v: f64) -> String {
+    // без шума двоичной арифметики: 0.1 + 0.2 -> 0.3, не 0.30000000000000004
+    format!("{v:.12e}").parse::<f64>().unwrap_or(v).to_string()
+}
+
+fn mpl(bu: &BaseUnits) -> Option<String> {
+    (bu.mpl != 1.0).then(|| num(bu.mpl))
+}
+
+// LaTeX
+
+fn tex_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '%' | '#' | '&' | '$' | '_' | '{' | '}' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '^' => out.push_str("\\^{}"),
+            '~' => out.push_str("\\textasciitilde{}"),
+            '\\' => out.push_str("\\textbackslash{}"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn tex_value(v: &Value) -> String {
+    match v.u {
+        None => v.v.to_string(),
+        Some(Uncertainty::Abs(u)) => format!("{}\\pm{u}", v.v),
+        Some(Uncertainty::Rel(r)) => format!("{}\\pm{}\\%", v.v, r * 100.0),
+    }
+}
+
+fn tex_factors(factors: &[Factor]) -> String {
+    // \cdot между множителями числителя, тонкий пробел перед отрицательными степенями
+    let mut out = String::new();
+    for f in factors {
+        if !out.is_empty() {
+            out += if f.pow < 0 { "\\," } else { "\\cdot" };
+        }
+        out += &format!("\\mathrm{{{}}}", tex_escape(&f.symbol()));
+        if f.pow != 1 {
+            out += &format!("^{{{}}}", f.pow);
+        }
+    }
+    if out.is_empty() { UNITY.to_owned() } else { out }
+}
+
+fn positive_first(mut factors: Vec<Factor>) -> Vec<Factor> {
+    factors.sort_by_key(|f| f.pow < 0);
+    factors
+}
+
+/// LaTeX (математический режим): \mathrm{кг}\cdot\mathrm{м}^{2}\,\mathrm{с}^{-2}
+pub fn latex_expr(expr: &Expr) -> String {
+    if let Expr::Convert(v, src, dst) = expr {
+        return format!("{}\\,{}\\rightarrow{}", tex_value(v), latex_expr(src), latex_expr(dst));
+    }
+    let mut factors = vec![];
+    expr_factors(expr, &mut factors);
+    tex_factors(&positive_first(factors))
+}
+
+/// LaTeX для разложения: множитель и базовые единицы
+pub fn latex(bu: &BaseUnits) -> String {
+    let factors = base_factors(bu);
+    let units = tex_factors(&factors);
+    match mpl(bu) {
+        Some(m) if factors.is_empty() => m,
+        Some(m) => format!("{m}\\,{units}"),
+        None => units,
+    }
+}
+
+fn si_factors(factors: &[Factor]) -> String {
+    // единицы siunitx в буквальном режиме: кг.м^{2}.с^{-2}; тег с точкой - в \text{}
+    factors.iter()
+        .map(|f| {
+            let sym = tex_escape(&f.symbol());
+            let sym = if sym.contains('.') { format!("\\text{{{sym}}}") } else { sym };
+            match f.pow {
+                1 => sym,
+                pow => format!("{sym}^{{{pow}}}"),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// siunitx: \si{кг.м^{2}.с^{-2}}, для Convert - \SI{1}{кПа}\rightarrow\si{атм};
+/// безразмерное - \num{...}
+pub fn siunitx_expr(expr: &Expr) -> String {
+    if let Expr::Convert(v, src, dst) = expr {
+        let val = match v.u {
+            None => v.v.to_string(),
+            Some(Uncertainty::Abs(u)) => format!("{}+-{u}", v.v),
+            Some(Uncertainty::Rel(r)) => format!("{}+-{}", v.v, (v.v * r).abs()),
+        };
+        let mut src_f = vec![];
+        expr_factors(src, &mut src_f);
+        let src = match src_f.is_empty() {
+            true => format!("\\num{{{val}}}"),
+            false => format!("\\SI{{{val}}}{{{}}}", si_factors(&positive_first(src_f))),
+        };
+        return format!("{src}\\rightarrow{}", siunitx_expr(dst));
+    }
+    let mut factors = vec![];
+    expr_factors(expr, &mut factors);
+    match factors.is_empty() {
+        true => format!("\\num{{{UNITY}}}"),
+        false => format!("\\si{{{}}}", si_factors(&positive_first(factors))),
+    }
+}
+
+/// siunitx для разложения: \SI{101325}{кг.м^{-1}.с^{-2}}, \si{...} при множителе 1,
+/// \num{1000} для безразмерного
+pub fn siunitx(bu: &BaseUnits) -> String {
+    let factors = base_factors(bu);
+    if factors.is_empty() {
+        return format!("\\num{{{}}}", mpl(bu).unwrap_or(UNITY.to_owned()));
+    }
+    let units = si_factors(&factors);
+    match mpl(bu) {
+        Some(m) => format!("\\SI{{{m}}}{{{units}}}"),
+        None => format!("\\si{{{units}}}"),
+    }
+}
+
+// MathML
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn mml_num(v: f64) -> String {
+    match v < 0.0 {
+        true => format!("<mo>-</mo><mn>{}</mn>", num(-v)),
+        false => format!("<mn>{}</mn>", num(v)),
+    }
+}
+
+fn mml_value(v: &Value) -> String {
+    match v.u {
+        None => mml_num(v.v),
+        Some(Uncertainty::Abs(u)) => format!("{}<mo>±</mo><mn>{u}</mn>", mml_num(v.v)),
+        Some(Uncertainty::Rel(r)) => format!("{}<mo>±</mo><mn>{}</mn><mo>%</mo>", mml_num(v.v), r * 100.0),
+    }
+}
+
+fn mml_factors(factors: &[Factor]) -> String {
+    if factors.is_empty() {
+        return format!("<mn>{UNITY}</mn>");
+    }
+    factors.iter()
+        .map(|f| {
+            let mi = format!("<mi mathvariant=\"normal\">{}</mi>", xml_escape(&f.symbol()));
+            match f.pow {
+                1 => mi,
+                pow => format!("<msup>{mi}<mrow>{}</mrow></msup>", mml_num(pow as f64)),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("<mo>⋅</mo>")
+}
+
+fn mml_math(inner: &str) -> String {
+    format!("<math xmlns=\"{MATHML_NS}\"><mrow>{inner}</mrow></math>")
+}
+
+fn mml_expr(expr: &Expr) -> String {
+    if let Expr::Convert(v, src, dst) = expr {
+        return format!("{}<mspace width=\"thinmathspace\"/>{}<mo>→</mo>{}", mml_value(v), mml_expr(src), mml_expr(dst));
+    }
+    let mut factors = vec![];
+    expr_factors(expr, &mut factors);
+    mml_factors(&positive_first(factors))
+}
+
+/// MathML: <math><mrow><mi>кг</mi><mo>⋅</mo><msup>...</msup></mrow></math>
+pub fn mathml_expr(expr: &Expr) -> String {
+    mml_math(&mml_expr(expr))
+}
+
+/// MathML для разложения: множитель и базовые единицы
+pub fn mathml(bu: &BaseUnits) -> String {
+    let factors = base_factors(bu);
+    let units = mml_factors(&factors);
+    mml_math(&match mpl(bu) {
+        Some(_) if factors.is_empty() => mml_num(bu.mpl),
+        Some(_) => format!("{}<mo>⋅</mo>{units}", mml_num(bu.mpl)),
+        None => units,
+    })
+}
@@ -0,0 +1,123 @@
+// RPC: line-delimited JSON-RPC 2.0 через stdio, по одному запросу на строку
+//
+// {"jsonrpc": "2.0", "id": 1, "method": "convert", "params": {"stmt": "1 к_Па=>атм"}}
+// методы: convert {stmt}, decompose {expr}, complete {prefix}, validate {stmt}
+
+use crate::common::{convert_json, decompose_json, error_kind};
+use crate::complete::complete;
+use crate::folder::{Folder, UnitsError};
+use crate::interpreter::Interpreter;
+use crate::parser::{enter_validation, parse_stmt, ParseError};
+use crate::ucum::UcumError;
+use crate::unicode::normalize;
+use serde_json::{json, Value};
+use std::error::Error;
+use std::io::{self, BufRead, Write};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+//@fmt:off
+const PARSE_ERROR: i32      = -32700;
+const INVALID_REQUEST: i32  = -32600;
+const NO_METHOD: i32        = -32601;
+const INVALID_PARAMS: i32   = -32602;
+const INTERNAL_ERROR: i32   = -32603;
+const DSL_PARSE_ERROR: i32  = -32001;
+const DSL_UNITS_ERROR: i32  = -32002;
+//@fmt:on
+
+pub fn run<R: BufRead, W: Write>(input: R, mut output: W, ii: &mut Interpreter) -> io::Result<()> {
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(resp) = handle_line(ii, &line) {
+            writeln!(output, "{resp}")?;
+            output.flush()?;
+        }
+    }
+    Ok(())
+}
+
+pub fn handle_line(ii: &mut Interpreter, line: &str) -> Option<Value> {
+    let req: Value = match serde_json::from_str(line) {
+        Ok(req) => req,
+        Err(e) => return Some(error(Value::Null, PARSE_ERROR, "ParseError", e.to_string())),
+    };
+    // без id - уведомление, ответ не нужен
+    let id = req.get("id")?.clone();
+
+    let Some(method) = req.get("method").and_then(Value::as_str) else {
+        return Some(error(id, INVALID_REQUEST, "InvalidRequest", "method is required".into()));
+    };
+    let params = req.get("params").cloned().unwrap_or(Value::Null);
+
+    Some(guarded(id.clone(), || {
+        let res = match method {
+            "convert" => param(&params, "stmt").map(|stmt| convert_json(ii, &stmt)),
+            "decompose" => param(&params, "expr").map(|expr| decompose_json(ii, &expr)),
+            "complete" => param(&params, "prefix").map(|prefix| Ok(json!(complete(&prefix)))),
+            "validate" => param(&params, "stmt").map(|stmt| Ok(validate(ii, &stmt))),
+            _ => return error(id.clone(), NO_METHOD, "MethodNotFound", method.to_owned()),
+        };
+
+        match res {
+            None => error(id.clone(), INVALID_PARAMS, "InvalidParams", format!("{params}")),
+            Some(Ok(result)) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+            Some(Err(e)) => error_response(id.clone(), e.as_ref()),
+        }
+    }))
+}
+
+/// Выполняет запрос под catch_unwind: паника - ответ Internal error, а не конец сессии
+pub(crate) fn guarded(id: Value, f: impl FnOnce() -> Value) -> Value {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(resp) => resp,
+        Err(_) => error(id, INTERNAL_ERROR, "InternalError", "internal error".into()),
+    }
+}
+
+pub(crate) fn error_response(id: Value, e: &(dyn Error + 'static)) -> Value {
+    if e.is::<UnitsError>() {
+        error(id, DSL_UNITS_ERROR, error_kind(e), e.to_string())
+    } else if e.is::<ParseError>() || e.is::<UcumError>() {
+        error(id, DSL_PARSE_ERROR, error_kind(e), e.to_string())
+    } else {
+        // ввод-вывод, словари и прочее - не ошибка в выражении
+        error(id, INTERNAL_ERROR, "InternalError", e.to_string())
+    }
+}
+
+fn param(params: &Value, name: &str) -> Option<String> {
+    // {"stmt": "..."} или ["..."]
+    params.get(name).or_else(|| params.get(0))?.as_str().map(str::to_owned)
+}
+
+fn error(id: Value, code: i32, kind: &str, message: String) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {"code": code, "message": message, "data": {"kind": kind}},
+    })
+}
+
+fn check(ii: &mut Interpreter, stmt: &str) -> Result<(), Box<dyn Error>> {
+    let stmt = &normalize(stmt);
+    enter_validation(stmt)?;
+    let stmt = parse_stmt(stmt)?;
+    ii.fold_stmt(&stmt)?;
+    Ok(())
+}
+
+fn validate(ii: &mut Interpreter, stmt: &str) -> Value {
+    match check(ii, stmt) {
+        Ok(()) => json!({"valid": true}),
+        Err(e) => {
+            let kind = match e.downcast_ref::<ParseError>() {
+                Some(_) => "parse",
+                None => "units",
+            };
+            json!({"valid": false, "stage": kind, "kind": error_kind(e.as_ref()), "message": e.to_string()})
+        }
+    }
+}
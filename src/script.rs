@@ -0,0 +1,123 @@
+// Script: файл .units - по одному Statement на строку,
+// комментарии после #, привязки let p = 12 к_Па и их использование: p=>атм
+
+use crate::interpreter::Interpreter;
+use crate::register::units;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+//@fmt:off
+const COMMENT: &str = "#";
+const LET: &str     = "let ";
+const EQ: &str      = " = ";
+const CONV: &str    = "=>";
+//@fmt:on
+
+#[derive(Debug, thiserror::Error)]
+pub enum ScriptError {
+    #[error("binding must be: let name = float unit_expr[=>unit_expr], got: {0}")]
+    WrongLet(String),
+
+    #[error("name {0} is already a unit")]
+    NameIsUnit(String),
+}
+
+#[derive(Debug)]
+pub struct LineResult {
+    pub line: usize,
+    pub out: Result<String, String>,
+}
+
+impl fmt::Display for LineResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.out {
+            Ok(out) => write!(f, "{:4}: {out}", self.line),
+            Err(err) => write!(f, "{:4}: error: {err}", self.line),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Script {
+    // name -> "value unit_expr"
+    pub bindings: HashMap<String, String>,
+}
+
+impl Script {
+    pub fn new() -> Script {
+        Self::default()
+    }
+
+    pub fn run(&mut self, ii: &mut Interpreter, src: &str) -> Vec<LineResult> {
+        let mut res = vec![];
+
+        for (i, line) in src.lines().enumerate() {
+            let stmt = match line.split_once(COMMENT) {
+                Some((stmt, _)) => stmt.trim(),
+                None => line.trim(),
+            };
+            if stmt.is_empty() {
+                continue;
+            }
+            let out = self.exec(ii, stmt).map_err(|e| e.to_string());
+            res.push(LineResult { line: i + 1, out });
+        }
+        res
+    }
+
+    pub fn exec(&mut self, ii: &mut Interpreter, stmt: &str) -> Result<String, Box<dyn Error>> {
+        if let Some(binding) = stmt.strip_prefix(LET) {
+            let (name, val) = match binding.split_once(EQ) {
+                Some((name, val)) if is_name(name) => (name, val),
+                _ => return Err(Box::new(ScriptError::WrongLet(stmt.to_owned()))),
+            };
+            if units().contains_key(name) {
+                return Err(Box::new(ScriptError::NameIsUnit(name.to_owned())));
+            }
+            let val = self.bind(ii, val)?;
+            let out = format!("{name} = {val}");
+            self.bindings.insert(name.to_owned(), val);
+            return Ok(out);
+        }
+
+        if let Some(val) = self.bindings.get(stmt) {
+            return Ok(format!("{stmt} = {val}"));
+        }
+        ii.exec(&self.substitute(stmt))
+    }
+
+    fn bind(&self, ii: &mut Interpreter, val: &str) -> Result<String, Box<dyn Error>> {
+        // значение привязки: "12 к_Па" или результат конвертации "12 к_Па=>атм"
+        let val = self.substitute(val);
+        match val.split_once(CONV) {
+            Some((_, dst)) => Ok(format!("{} {dst}", ii.try_conv_measured(&val)?)),
+            None => {
+                // проверяем значение конвертацией в те же единицы
+                let Some((_, expr)) = val.split_once(' ') else {
+                    return Err(Box::new(ScriptError::WrongLet(val)));
+                };
+                ii.try_conv_measured(&format!("{val}{CONV}{expr}"))?;
+                Ok(val)
+            }
+        }
+    }
+
+    fn substitute(&self, stmt: &str) -> String {
+        // p=>атм  ->  12 к_Па=>атм
+        match stmt.split_once(CONV) {
+            Some((name, dst)) => match self.bindings.get(name) {
+                Some(val) => format!("{val}{CONV}{dst}"),
+                None => stmt.to_owned(),
+            },
+            None => stmt.to_owned(),
+        }
+    }
+}
+
+fn is_name(name: &str) -> bool {
+    // имя привязки не должно читаться как единица с приставкой или степенью
+    !name.is_empty()
+        && name.parse::<f64>().is_err()
+        && !name.contains(|c: char| "_^*/=>±# ".contains(c))
+}
@@ -0,0 +1,200 @@
+// Server: HTTP/JSON сервис конвертации поверх std::net, без сторонних библиотек
+//
+// POST /convert   {"stmt": "1 к_Па=>атм"}
+// POST /decompose {"expr": "Па/дм^2"}
+// POST /compatible {"expr": "Па", "prefixed": false}
+// GET  /units
+// GET  /prefixes
+
+use crate::common::{convert_json, decompose_json, error_kind};
+use crate::folder::UnitsError;
+use crate::interpreter::Interpreter;
+use crate::register::{prefixes, units};
+use serde_json::{json, Value};
+use std::error::Error;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+// ограничения на соединение: размер тела, заголовков, таймауты и число потоков
+const MAX_BODY: usize = 64 * 1024;
+const MAX_HEAD: u64 = 16 * 1024;
+const TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_CONN: usize = 64;
+
+pub struct Response {
+    pub status: u16,
+    pub body: Value,
+}
+
+impl Response {
+    fn ok(body: Value) -> Response {
+        Self { status: 200, body }
+    }
+
+    fn err(status: u16, kind: &str, message: String) -> Response {
+        let body = json!({"error": {"kind": kind, "message": message}});
+        Self { status, body }
+    }
+}
+
+// занятый слот соединения; освобождается и при панике в обработчике
+struct Slot(Arc<AtomicUsize>);
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub fn serve(addr: &str) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    let ii = Arc::new(Mutex::new(Interpreter::new()));
+    println!("listening on {}", listener.local_addr()?);
+
+    let active = Arc::new(AtomicUsize::new(0));
+
+    for stream in listener.incoming() {
+        // ошибка одного соединения не останавливает сервер
+        let stream = match stream {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("{e}");
+                continue;
+            }
+        };
+        if let Err(e) = stream.set_read_timeout(Some(TIMEOUT)).and_then(|_| stream.set_write_timeout(Some(TIMEOUT))) {
+            eprintln!("{e}");
+            continue;
+        }
+
+        if active.fetch_add(1, Ordering::SeqCst) >= MAX_CONN {
+            active.fetch_sub(1, Ordering::SeqCst);
+            let resp = Response::err(503, "ServiceUnavailable", "too many connections".into());
+            if let Err(e) = write_response(&stream, &resp) {
+                eprintln!("{e}");
+            }
+            continue;
+        }
+
+        let ii = Arc::clone(&ii);
+        let slot = Slot(Arc::clone(&active));
+        thread::spawn(move || {
+            let _slot = slot;
+            if let Err(e) = handle_conn(stream, &ii) {
+                eprintln!("{e}");
+            }
+        });
+    }
+    Ok(())
+}
+
+pub(crate) fn handle_conn(stream: TcpStream, ii: &Mutex<Interpreter>) -> std::io::Result<()> {
+    // заголовки не длиннее MAX_HEAD, тело читается отдельно после проверки длины
+    let mut reader = BufReader::new((&stream).take(MAX_HEAD));
+
+    let mut start = String::new();
+    reader.read_line(&mut start)?;
+    let mut parts = start.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_owned();
+    let path = parts.next().unwrap_or_default().to_owned();
+
+    // headers: нужен только Content-Length
+    let mut len = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, val)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                len = val.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    if len > MAX_BODY {
+        let resp = Response::err(413, "PayloadTooLarge", format!("body is limited to {MAX_BODY} bytes"));
+        return write_response(&stream, &resp);
+    }
+    let mut body = vec![0; len];
+    reader.get_mut().set_limit(len as u64);
+    reader.read_exact(&mut body)?;
+
+    let resp = handle(ii, &method, &path, &String::from_utf8_lossy(&body));
+    write_response(&stream, &resp)
+}
+
+fn write_response(mut stream: &TcpStream, resp: &Response) -> std::io::Result<()> {
+    let body = resp.body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        resp.status,
+        reason(resp.status),
+        body.len(),
+        body
+    )
+}
+
+pub fn handle(ii: &Mutex<Interpreter>, method: &str, path: &str, body: &str) -> Response {
+    // паника в другом запросе не должна отключать сервис
+    let mut ii = ii.lock().unwrap_or_else(|e| e.into_inner());
+
+    let res = match (method, path) {
+        ("POST", "/convert") => field(body, "stmt").and_then(|stmt| convert_json(&mut ii, &stmt)),
+        ("POST", "/decompose") => field(body, "expr").and_then(|expr| decompose_json(&mut ii, &expr)),
+        ("POST", "/compatible") => field(body, "expr").and_then(|expr| {
+            let prefixed = serde_json::from_str::<Value>(body)?["prefixed"].as_bool().unwrap_or(false);
+            Ok(json!(ii.compatible_units(&expr, prefixed)?))
+        }),
+        ("GET", "/units") => {
+            let mut tags: Vec<String> = units().keys().cloned().collect();
+            tags.sort();
+            Ok(json!(tags))
+        }
+        ("GET", "/prefixes") => Ok(json!(prefixes())),
+        (_, "/convert" | "/decompose" | "/compatible" | "/units" | "/prefixes") => {
+            return Response::err(405, "MethodNotAllowed", format!("{method} {path}"));
+        }
+        _ => return Response::err(404, "NotFound", path.to_owned()),
+    };
+
+    match res {
+        Ok(body) => Response::ok(body),
+        Err(e) => error_response(e.as_ref()),
+    }
+}
+
+fn field(body: &str, name: &str) -> Result<String, Box<dyn Error>> {
+    let req: Value = serde_json::from_str(body)?;
+    match req.get(name).and_then(Value::as_str) {
+        Some(v) => Ok(v.to_owned()),
+        None => Err(format!("field '{name}' is required").into()),
+    }
+}
+
+fn error_response(e: &(dyn Error + 'static)) -> Response {
+    // ParseError - 400, UnitsError - 404 для неизвестных единиц, иначе 422
+    let status = match e.downcast_ref::<UnitsError>() {
+        Some(UnitsError::NoUnit(_) | UnitsError::NoUnitPrefix(_)) => 404,
+        Some(_) => 422,
+        None => 400,
+    };
+    Response::err(status, error_kind(e), e.to_string())
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        422 => "Unprocessable Entity",
+        503 => "Service Unavailable",
+        _ => "",
+    }
+}
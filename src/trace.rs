@@ -0,0 +1,149 @@
+// Trace: пошаговое объяснение вычисления мультипликатора (explain mode)
+
+use crate::ast::{Expr, Stmt};
+use crate::folder::UnitsError;
+use crate::register::prefixes;
+use crate::units::{reduce_traced, Unit};
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    Stmt(String),
+    Src,
+    Dst,
+    // единица как она разобрана, pow со знаком (знаменатель - отрицательный)
+    Unit { pfx: Option<String>, tag: String, pow: i8 },
+    // приставка: 10^exp в степени pow
+    Prefix { pfx: String, exp: i32, pow: i8 },
+    // раскрыто определение из словаря
+    Define { tag: String, pow: i8 },
+    // множитель из определения в степени родителя
+    Power { tag: String, mpl: f64, pow: i8 },
+    // базовая единица
+    Base { tag: String, pow: i8 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trace {
+    pub step: Step,
+    // накопленный мультипликатор поддерева
+    pub mpl: f64,
+    pub children: Vec<Trace>,
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::Stmt(s) => write!(f, "{s}"),
+            Step::Src => write!(f, "src"),
+            Step::Dst => write!(f, "dst (divides)"),
+            Step::Unit { pfx: Some(p), tag, pow } => write!(f, "unit {p}_{tag}^{pow}"),
+            Step::Unit { pfx: None, tag, pow } => write!(f, "unit {tag}^{pow}"),
+            Step::Prefix { pfx, exp, pow } => write!(f, "prefix {pfx} = (10^{exp})^{pow}"),
+            Step::Define { tag, pow } => write!(f, "define {tag}^{pow}"),
+            Step::Power { tag, mpl, pow } => write!(f, "factor {mpl} of {tag}, ^{pow}"),
+            Step::Base { tag, pow } => write!(f, "base {tag}^{pow}"),
+        }
+    }
+}
+
+impl Trace {
+    pub(crate) fn new(step: Step, mpl: f64, children: Vec<Trace>) -> Trace {
+        Self { step, mpl, children }
+    }
+
+    fn fmt_tree(&self, f: &mut fmt::Formatter<'_>, indent: &str, last: bool, root: bool) -> fmt::Result {
+        let (branch, next) = match (root, last) {
+            (true, _) => ("", String::new()),
+            (false, true) => ("└─ ", format!("{indent}   ")),
+            (false, false) => ("├─ ", format!("{indent}│  ")),
+        };
+        writeln!(f, "{indent}{branch}{} [x {}]", self.step, self.mpl)?;
+
+        for (i, c) in self.children.iter().enumerate() {
+            c.fmt_tree(f, &next, i + 1 == self.children.len(), false)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_tree(f, "", true, true)
+    }
+}
+
+pub fn explain_stmt(label: &str, s: &Stmt, voc: &HashMap<String, Unit>) -> Result<Trace, UnitsError> {
+    match s {
+        Stmt::Conversation(Expr::Convert(_, src, dst)) => {
+            let src = Trace { step: Step::Src, ..explain_expr(src, voc)? };
+            let dst = Trace { step: Step::Dst, ..explain_expr(dst, voc)? };
+            let mpl = src.mpl / dst.mpl;
+            Ok(Trace::new(Step::Stmt(label.to_owned()), mpl, vec![src, dst]))
+        }
+        Stmt::Conversation(e) | Stmt::Decomposition(e) => {
+            Ok(Trace { step: Step::Stmt(label.to_owned()), ..explain_expr(e, voc)? })
+        }
+        Stmt::Definition { val, expr, .. } => {
+            let t = explain_expr(expr, voc)?;
+            Ok(Trace::new(Step::Stmt(label.to_owned()), val.v * t.mpl, vec![t]))
+        }
+    }
+}
+
+fn explain_expr(e: &Expr, voc: &HashMap<String, Unit>) -> Result<Trace, UnitsError> {
+    match e {
+        Expr::Fraction { up, down } => {
+            let mut mpl = 1.0;
+            let mut children = vec![];
+            for u in up.iter().chain(down.iter()) {
+                let t = explain_expr(u, voc)?;
+                mpl *= t.mpl;
+                children.push(t);
+            }
+            Ok(Trace::new(Step::Src, mpl, children))
+        }
+        Expr::Unit { pfx, tag, pow, den } => {
+            let pow = match den {
+                true => pow.checked_neg().ok_or_else(|| UnitsError::PowOverflow(tag.clone()))?,
+                false => *pow,
+            };
+            let mut children = vec![];
+            let mut mpl = 1.0;
+
+            if let Some(p) = pfx {
+                let exp = match prefixes().get(p.as_str()) {
+                    Some(exp) => *exp,
+                    None => return Err(UnitsError::NoUnitPrefix(p.clone())),
+                };
+                let m = 10f64.powi(exp).powi(pow as i32);
+                children.push(Trace::new(Step::Prefix { pfx: p.clone(), exp, pow }, m, vec![]));
+                mpl *= m;
+            }
+            if !voc.contains_key(tag) {
+                return Err(UnitsError::NoUnit(tag.clone()));
+            }
+            let t = explain_unit(tag, pow, voc)?;
+            mpl *= t.mpl;
+            children.push(t);
+
+            let step = Step::Unit { pfx: pfx.clone(), tag: tag.clone(), pow };
+            Ok(Trace::new(step, mpl, children))
+        }
+        Expr::Convert(..) => Err(UnitsError::NoUnit("sorry".into())),
+    }
+}
+
+fn explain_unit(tag: &str, pow: i8, voc: &HashMap<String, Unit>) -> Result<Trace, UnitsError> {
+    // шаги записывает сама свертка (reduce_traced), отдельного подсчета множителя нет
+    let mut proto = voc[tag].clone();
+    if proto.base.is_empty() {
+        return Ok(Trace::new(Step::Base { tag: tag.to_owned(), pow }, 1.0, vec![]));
+    }
+    proto.pow = pow;
+    proto.mpl = 1.0;
+    let mut children = vec![];
+    let (mpl, _) = reduce_traced(&proto, voc, Some(&mut children))?;
+    Ok(Trace::new(Step::Define { tag: tag.to_owned(), pow }, mpl, children))
+}
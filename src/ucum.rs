@@ -0,0 +1,292 @@
+// UCUM: второй front-end (коды UCUM -> ast::Stmt) и вывод BaseUnits кодом UCUM
+//
+// "kg.m/s2"       -> м^1 * кг^1 * с^-2
+// "mm[Hg]"        -> мм.рт.ст
+// "10*3.m"        -> да_1^3 * м, степень десяти переходит в безразмерную "1" с приставкой
+// "1 kPa=>mm[Hg]" -> конвертация, как в основном DSL
+
+use crate::ast::{Expr, Stmt};
+use crate::common::impl_error_kind;
+use crate::parser::{parse_value, ParseError, CONV, WS};
+use crate::units::{BaseUnits, UNITY};
+
+#[derive(Debug, thiserror::Error)]
+pub enum UcumError {
+    #[error("unknown UCUM unit: {0}")]
+    UnknownAtom(String),
+
+    #[error("UCUM syntax error at {1}: {0}")]
+    WrongSyntax(String, usize),
+
+    #[error("UCUM exponent out of range: {0}")]
+    WrongExponent(String),
+
+    #[error("only powers of ten are supported as UCUM factors: {0}")]
+    UnsupportedFactor(String),
+
+    #[error(transparent)]
+    Value(#[from] ParseError),
+}
+
+// ошибка разбора значения - по варианту ParseError
+impl_error_kind!(UcumError: UnknownAtom, WrongSyntax, WrongExponent, UnsupportedFactor; Value);
+
+//@fmt:off
+// код UCUM, тег словаря, допустимы ли приставки
+const ATOMS: [(&str, &str, bool); 37] = [
+    ("m",      "м",         true),
+    ("kg",     "кг",        false),
+    ("g",      "г",         true),
+    ("s",      "с",         true),
+    ("A",      "А",         true),
+    ("K",      "К",         true),
+    ("mol",    "моль",      true),
+    ("cd",     "кд",        true),
+    ("Hz",     "Гц",        true),
+    ("Bq",     "Бк",        true),
+    ("N",      "Н",         true),
+    ("J",      "Дж",        true),
+    ("W",      "Вт",        true),
+    ("Pa",     "Па",        true),
+    ("C",      "Кл",        true),
+    ("V",      "В",         true),
+    ("F",      "Ф",         true),
+    ("Ohm",    "Ом",        true),
+    ("T",      "Тл",        true),
+    ("Wb",     "Вб",        true),
+    ("H",      "Гн",        true),
+    ("min",    "мин",       false),
+    ("h",      "ч",         false),
+    ("d",      "сут",       false),
+    ("wk",     "нед",       false),
+    ("t",      "т",         true),
+    ("ar",     "а",         true),
+    ("atm",    "атм",       false),
+    ("att",    "ат",        false),
+    ("mm[Hg]", "мм.рт.ст",  false),
+    ("mm[H2O]","мм.вод.ст", false),
+    ("dyn",    "дин",       true),
+    ("cal",    "кал",       true),
+    ("rad",    "рад",       true),
+    ("sr",     "ср",        true),
+    ("deg",    "°",         false),
+    ("%",      "%",         false),
+];
+
+// приставки UCUM -> приставки словаря; da раньше d
+const PREFIXES: [(&str, &str); 12] = [
+    ("da", "да"),
+    ("T",  "Т"),
+    ("G",  "Г"),
+    ("M",  "М"),
+    ("k",  "к"),
+    ("h",  "г"),
+    ("d",  "д"),
+    ("c",  "с"),
+    ("m",  "м"),
+    ("u",  "мк"),
+    ("n",  "н"),
+    ("p",  "п"),
+];
+//@fmt:on
+
+const TEN: &str = "да";
+
+pub fn parse_stmt(input: &str) -> Result<Stmt, UcumError> {
+    // [float][WS][ucum][CONV][ucum] или [ucum]
+    let input = input.trim();
+    let Some((src, dst)) = input.split_once(CONV) else {
+        return Ok(Stmt::Decomposition(parse_expr(input)?));
+    };
+    let Some((val, src)) = src.split_once(WS) else {
+        return Err(UcumError::WrongSyntax(input.to_owned(), 0));
+    };
+    if dst.contains(CONV) {
+        return Err(ParseError::ExactlyOneExprSeparator().into());
+    }
+
+    Ok(Stmt::Conversation(Expr::Convert(
+        parse_value(val)?,
+        Box::new(parse_expr(src)?),
+        Box::new(parse_expr(dst)?),
+    )))
+}
+
+pub fn parse_expr(input: &str) -> Result<Expr, UcumError> {
+    let mut c = Cursor { src: input, pos: 0 };
+    let units = c.term()?;
+    if c.pos != input.len() {
+        return Err(UcumError::WrongSyntax(input.to_owned(), c.pos));
+    }
+
+    let mut up = vec![];
+    let mut down = vec![];
+    for (pfx, tag, pow) in units {
+        let den = pow < 0;
+        let pow = i8::try_from(pow.abs()).map_err(|_| UcumError::WrongExponent(input.to_owned()))?;
+        let unit = Expr::Unit { pfx, tag, pow, den };
+        if den { down.push(unit) } else { up.push(unit) }
+    }
+    Ok(Expr::Fraction { up, down })
+}
+
+// приставка словаря, тег словаря, степень
+type Component = (Option<String>, String, i32);
+
+struct Cursor<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl Cursor<'_> {
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn term(&mut self) -> Result<Vec<Component>, UcumError> {
+        // term: ['/'] component (('.' | '/') component)*, '/' относится к одному компоненту
+        let mut sign = 1;
+        if self.peek() == Some('/') {
+            self.pos += 1;
+            sign = -1;
+        }
+
+        let mut out = vec![];
+        loop {
+            out.extend(self.component()?.into_iter().map(|(p, t, pow)| (p, t, pow * sign)));
+            sign = match self.peek() {
+                Some('.') => 1,
+                Some('/') => -1,
+                _ => return Ok(out),
+            };
+            self.pos += 1;
+        }
+    }
+
+    fn component(&mut self) -> Result<Vec<Component>, UcumError> {
+        if self.peek() == Some('(') {
+            self.pos += 1;
+            let inner = self.term()?;
+            if self.peek() != Some(')') {
+                return Err(UcumError::WrongSyntax(self.src.to_owned(), self.pos));
+            }
+            self.pos += 1;
+            return Ok(inner);
+        }
+
+        // до разделителя, не заходя внутрь [...] и {...}
+        let start = self.pos;
+        let mut depth = 0;
+        while let Some(ch) = self.peek() {
+            match ch {
+                '[' | '{' => depth += 1,
+                ']' | '}' => depth -= 1,
+                '.' | '/' | '(' | ')' if depth == 0 => break,
+                _ => (),
+            }
+            self.pos += ch.len_utf8();
+        }
+        simple(&self.src[start..self.pos]).map_err(|e| match e {
+            UcumError::WrongSyntax(s, _) => UcumError::WrongSyntax(s, start),
+            e => e,
+        })
+    }
+}
+
+fn simple(token: &str) -> Result<Vec<Component>, UcumError> {
+    // аннотации {...} на величину не влияют
+    let mut code = String::new();
+    let mut depth = 0;
+    for ch in token.chars() {
+        match ch {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ if depth == 0 => code.push(ch),
+            _ => (),
+        }
+    }
+    if code.is_empty() {
+        return match token.is_empty() {
+            true => Err(UcumError::WrongSyntax(token.to_owned(), 0)),
+            false => Ok(vec![]),
+        };
+    }
+
+    // 10*3, 10^-2
+    if let Some(exp) = code.strip_prefix("10*").or(code.strip_prefix("10^")) {
+        let exp = exp.parse().map_err(|_| UcumError::WrongExponent(token.to_owned()))?;
+        return Ok(vec![(Some(TEN.to_owned()), UNITY.to_owned(), exp)]);
+    }
+    // целое: 1, 100, 1000
+    if code.chars().all(|c| c.is_ascii_digit()) {
+        let zeros = code.trim_start_matches('1');
+        return match code.starts_with('1') && code.len() - zeros.len() == 1 && zeros.chars().all(|c| c == '0') {
+            true => Ok(vec![(Some(TEN.to_owned()), UNITY.to_owned(), zeros.len() as i32)]),
+            false => Err(UcumError::UnsupportedFactor(token.to_owned())),
+        };
+    }
+
+    let (atom, pow) = split_exponent(&code);
+    let pow = match pow {
+        "" => 1,
+        p => p.parse().map_err(|_| UcumError::WrongExponent(token.to_owned()))?,
+    };
+    let (pfx, tag) = resolve(atom).ok_or_else(|| UcumError::UnknownAtom(atom.to_owned()))?;
+    Ok(vec![(pfx, tag, pow)])
+}
+
+fn split_exponent(code: &str) -> (&str, &str) {
+    // m2, s-1, s+2; после ']' или буквы
+    let digits = code.trim_end_matches(|c: char| c.is_ascii_digit());
+    if digits.len() == code.len() || digits.is_empty() {
+        return (code, "");
+    }
+    let atom = digits.strip_suffix(['-', '+']).unwrap_or(digits);
+    (atom, &code[atom.len()..])
+}
+
+fn resolve(atom: &str) -> Option<(Option<String>, String)> {
+    if let Some((_, tag, _)) = ATOMS.iter().find(|(code, ..)| *code == atom) {
+        return Some((None, tag.to_string()));
+    }
+    PREFIXES.iter().find_map(|(p, pfx)| {
+        let rest = atom.strip_prefix(p)?;
+        let (_, tag, _) = ATOMS.iter().find(|(code, _, metric)| *metric && *code == rest)?;
+        Some((Some(pfx.to_string()), tag.to_string()))
+    })
+}
+
+fn factor(mpl: f64) -> Result<Option<String>, UcumError> {
+    // множитель в коде UCUM - только степень десяти, как и при разборе
+    if mpl == 1.0 {
+        return Ok(None);
+    }
+    let exp = mpl.abs().log10().round() as i32;
+    if mpl > 0.0 && (10f64.powi(exp) - mpl).abs() <= mpl * 1e-12 {
+        return Ok(Some(format!("10*{exp}")));
+    }
+    Err(UcumError::UnsupportedFactor(mpl.to_string()))
+}
+
+/// Код UCUM единицы словаря: Ом -> Ohm, мм.рт.ст -> mm[Hg]
+pub(crate) fn atom_code(tag: &str) -> Option<&'static str> {
+    ATOMS.iter().find(|(_, t, _)| *t == tag).map(|(code, ..)| *code)
+}
+
+/// BaseUnits как код UCUM: 10*3.kg.m-1.s-2; единицы без кода UCUM - в скобках: [tag].
+/// Множитель, не являющийся степенью десяти (мм.рт.ст = 133.322387415 Па), - UnsupportedFactor
+pub fn render(bu: &BaseUnits) -> Result<String, UcumError> {
+    let mut parts: Vec<String> = factor(bu.mpl)?.into_iter().collect();
+    for u in bu.canonical() {
+        let code = atom_code(&u.tag).map_or(format!("[{}]", u.tag), str::to_owned);
+        parts.push(match u.pow {
+            1 => code,
+            pow => format!("{code}{pow}"),
+        });
+    }
+
+    Ok(match parts.is_empty() {
+        true => UNITY.to_owned(),
+        false => parts.join("."),
+    })
+}
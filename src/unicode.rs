@@ -0,0 +1,190 @@
+// Unicode: приведение вставленного текста к синтаксису DSL и обратный "красивый" вывод
+//
+// "1 кг·м²=>Н·с²"  -> "1 кг*м^2=>Н*с^2"
+// "1 м·с⁻¹=>км/ч"  -> "1 м*с^-1=>км/ч"
+// "1\u{a0}kг=>т"   -> "1 кг=>т", латинская k похожа на кириллическую к
+// "руб/кВт*ч"      -> "руб/к_Вт*ч", только normalize_split: приставка без "_"
+// "[кг^1 * м^-1 * с^-2]" -> "[кг·м⁻¹·с⁻²]"
+
+use crate::parser::{DEF, PW, US, WS};
+use crate::register::{prefixes, units};
+
+//@fmt:off
+const SUPERSCRIPTS: [(char, char); 12] = [
+    ('⁰', '0'), ('¹', '1'), ('²', '2'), ('³', '3'), ('⁴', '4'),
+    ('⁵', '5'), ('⁶', '6'), ('⁷', '7'), ('⁸', '8'), ('⁹', '9'),
+    ('⁻', '-'), ('⁺', '+'),
+];
+
+// латиница, которую при вставке путают с кириллицей
+const LOOKALIKES: [(char, char); 20] = [
+    ('A', 'А'), ('B', 'В'), ('C', 'С'), ('E', 'Е'), ('H', 'Н'),
+    ('K', 'К'), ('M', 'М'), ('O', 'О'), ('P', 'Р'), ('T', 'Т'),
+    ('X', 'Х'), ('a', 'а'), ('c', 'с'), ('e', 'е'), ('k', 'к'),
+    ('m', 'м'), ('o', 'о'), ('p', 'р'), ('x', 'х'), ('y', 'у'),
+];
+//@fmt:on
+
+fn map_char(c: char) -> Option<char> {
+    match c {
+        '·' | '⋅' | '∙' | '•' | '×' => Some('*'),
+        '⁄' | '∕' | '÷' => Some('/'),
+        '−' | '‐' | '‑' | '‒' | '–' => Some('-'),
+        '\u{200b}' | '\u{200c}' | '\u{200d}' | '\u{feff}' => None,
+        c if c.is_whitespace() => Some(' '),
+        c => Some(c),
+    }
+}
+
+fn superscript(c: char) -> Option<char> {
+    SUPERSCRIPTS.iter().find(|(s, _)| *s == c).map(|(_, d)| *d)
+}
+
+// замена символов: надстрочные степени, точки умножения, минусы и пробелы
+fn normalize_chars(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut in_pow = false;
+    for c in input.chars() {
+        if let Some(d) = superscript(c) {
+            if !in_pow && !out.ends_with(PW) {
+                out.push_str(PW);
+            }
+            in_pow = true;
+            out.push(d);
+            continue;
+        }
+        in_pow = false;
+        if let Some(c) = map_char(c) {
+            out.push(c);
+        }
+    }
+    out.trim().to_owned()
+}
+
+fn swap_lookalikes(s: &str, to_cyrillic: bool) -> String {
+    s.chars()
+        .map(|c| {
+            LOOKALIKES.iter()
+                .find(|(lat, cyr)| c == if to_cyrillic { *lat } else { *cyr })
+                .map_or(c, |(lat, cyr)| if to_cyrillic { *cyr } else { *lat })
+        })
+        .collect()
+}
+
+fn resolve(s: &str, known: impl Fn(&str) -> bool) -> String {
+    // неизвестный тег, который после замены похожих букв находится в словаре
+    if known(s) {
+        return s.to_owned();
+    }
+    [true, false].into_iter()
+        .map(|cyr| swap_lookalikes(s, cyr))
+        .find(|t| known(t))
+        .unwrap_or(s.to_owned())
+}
+
+fn split_prefix(tag: &str, known: impl Fn(&str) -> bool) -> Option<String> {
+    // приставка без разделителя: кВт -> к_Вт, мкм -> мк_м (длинные приставки раньше)
+    let mut pfxs: Vec<&str> = prefixes().into_keys().collect();
+    pfxs.sort_by_key(|p| std::cmp::Reverse(p.len()));
+    pfxs.into_iter().find_map(|p| {
+        let tag = resolve(tag.strip_prefix(p)?, &known);
+        known(&tag).then(|| format!("{p}{US}{tag}"))
+    })
+}
+
+fn normalize_token(token: &str, split: bool) -> String {
+    // значение не трогаем: 1.5±0.1
+    if token.is_empty() || token.starts_with(|c: char| c.is_ascii_digit() || "+-.".contains(c)) {
+        return token.to_owned();
+    }
+    let (unit, pow) = token.split_once(PW).map_or((token, None), |(u, p)| (u, Some(p)));
+
+    let voc = units();
+    let unit = match unit.split_once(US) {
+        Some((pfx, tag)) => {
+            let pfx = resolve(pfx, |p| prefixes().contains_key(p));
+            format!("{pfx}{US}{}", resolve(tag, |t| voc.contains_key(t)))
+        }
+        None => match resolve(unit, |t| voc.contains_key(t)) {
+            tag if split && !voc.contains_key(&tag) => split_prefix(&tag, |t| voc.contains_key(t)).unwrap_or(tag),
+            tag => tag,
+        },
+    };
+    match pow {
+        Some(p) => format!("{unit}{PW}{p}"),
+        None => unit,
+    }
+}
+
+/// Приведение ввода к синтаксису DSL перед enter_validation
+pub fn normalize(input: &str) -> String {
+    normalize_with(input, false)
+}
+
+/// normalize, который ещё и отделяет приставку у неизвестного тега: кВт -> к_Вт
+pub fn normalize_split(input: &str) -> String {
+    normalize_with(input, true)
+}
+
+fn normalize_with(input: &str, split: bool) -> String {
+    let input = normalize_chars(input);
+    // новый тег в def остаётся как есть: def кмоль = 1000 моль,
+    // лишние пробелы сохраняются - их отвергнет enter_validation
+    if let Some(r) = input.strip_prefix(DEF).filter(|r| r.starts_with(WS)) {
+        let body = r.trim_start_matches(WS);
+        if let Some((tag, rest)) = body.split_once(WS) {
+            return format!("{DEF}{}{tag}{WS}{}", &r[..r.len() - body.len()], normalize_tokens(rest, split));
+        }
+    }
+    normalize_tokens(&input, split)
+}
+
+fn normalize_tokens(input: &str, split: bool) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut token = String::new();
+    for c in input.chars() {
+        if " */=>".contains(c) {
+            out += &normalize_token(&token, split);
+            token.clear();
+            out.push(c);
+        } else {
+            token.push(c);
+        }
+    }
+    out += &normalize_token(&token, split);
+    out
+}
+
+/// Вывод со степенями надстрочными символами и точкой умножения: кг·м⁻¹·с⁻²
+pub fn pretty(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '^' => {
+                let mut pow = String::new();
+                while let Some(&d) = chars.peek() {
+                    if !(d.is_ascii_digit() || (pow.is_empty() && (d == '-' || d == '+'))) {
+                        break;
+                    }
+                    pow.push(d);
+                    chars.next();
+                }
+                if pow != "1" {
+                    out.extend(pow.chars().map(|d| SUPERSCRIPTS.iter().find(|(_, a)| *a == d).map_or(d, |(s, _)| *s)));
+                }
+            }
+            ' ' if chars.peek() == Some(&'*') => {
+                // " * " -> "·"
+                chars.next();
+                if chars.peek() == Some(&' ') {
+                    chars.next();
+                }
+                out.push('·');
+            }
+            '*' => out.push('·'),
+            c => out.push(c),
+        }
+    }
+    out
+}
@@ -1,9 +1,17 @@
+use crate::ast::{Expr, Value};
+use crate::parser::{parse_expr, parse_value, ParseError, DIV, MUL, PW, WS};
 use crate::register::prefixes;
 use crate::folder::UnitsError;
 use crate::register;
-use crate::register::units;
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use crate::register::{units, Registry};
+use crate::measured::Measured;
+use crate::trace::{Step, Trace};
+use serde::de::value::SeqAccessDeserializer;
+use serde::de::{self, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::error::Error;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
 use std::sync::MutexGuard;
 
 fn f64_one() -> f64 {
@@ -16,6 +24,12 @@ fn vec_unit_empty() -> Vec<Unit> {
     vec![]
 }
 
+// безразмерная единица: 1 %, 1 ppm, 1 рад/рад - в базовых единицах сокращается
+pub const UNITY: &str = "1";
+
+// словарь единиц СИ: основные и производные когерентные единицы
+const SI_VOC: &str = "c_units";
+
 #[derive(Debug, Clone)]
 pub struct ParsedUnit {
     // DTO for parsed data
@@ -33,8 +47,126 @@ pub struct Unit {
     pub mpl: f64, // мультипликатор
     #[serde(default = "i8_one")]
     pub(crate) pow: i8, // экспонента
-    #[serde(default = "vec_unit_empty")]
+    #[serde(default = "vec_unit_empty", deserialize_with = "de_base")]
     pub base: Vec<Unit>,
+    // псевдо-размерность (рад, ср): может быть стёрта при проверке совместимости
+    #[serde(default)]
+    pub pseudo: bool,
+    // логарифмическая единица (дБ, Нп, pH): base задаёт опорный уровень
+    #[serde(default)]
+    pub log: Option<LogScale>,
+    // род величины (energy, torque, frequency, ...): Дж и Н*м одной размерности
+    #[serde(default)]
+    pub kind: Option<String>,
+    // относительная неопределенность определения (0 - точное определение)
+    #[serde(default)]
+    pub unc: f64,
+    // справочные поля словаря, на вычисления не влияют
+    #[serde(flatten)]
+    pub meta: UnitMeta,
+    // словарь-источник "name@version", заполняется при загрузке
+    #[serde(skip)]
+    pub voc: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UnitMeta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    // международное обозначение: Па -> Pa
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plural: Option<String>,
+    // причина, по которой единицу не рекомендуется использовать
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<String>,
+    // явный признак точного определения, иначе выводится из unc
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exact: Option<bool>,
+}
+
+impl Unit {
+    /// Приставки применимы к единицам СИ (c_units, рад, ср) и грамму;
+    /// кг уже с приставкой, мин, атм, км и т.п. - нет
+    pub fn accepts_prefixes(&self) -> bool {
+        let si = self.voc.as_deref().and_then(|v| v.split('@').next()) == Some(SI_VOC);
+        self.tag == "г" || (self.tag != "кг" && (si || self.pseudo))
+    }
+
+    pub fn new(tag: &str, mpl: f64, pow: i8) -> Unit {
+        Self {
+            tag: tag.to_owned(),
+            mpl,
+            pow,
+            base: vec![],
+            pseudo: false,
+            log: None,
+            kind: None,
+            unc: 0.0,
+            meta: UnitMeta::default(),
+            voc: None,
+        }
+    }
+
+    pub fn is_exact(&self) -> bool {
+        self.meta.exact.unwrap_or(self.unc == 0.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LogScale {
+    // L = mul * log_base(x / ref)
+    pub base: f64,
+    pub mul: f64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum ExprKind {
+    // только базовые единицы: совместимо с любым родом величины
+    #[default]
+    Any,
+    // единица с известным родом: Дж, к_Па, Гц, или составное выражение
+    // с объявленным родом (DERIVED_KINDS): Н*м, к_Вт*ч
+    Of(String),
+    // составное выражение без объявленного рода: совместимо с любым родом
+    Unknown,
+}
+
+impl fmt::Display for ExprKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExprKind::Any => write!(f, "any"),
+            ExprKind::Of(k) => write!(f, "{k}"),
+            ExprKind::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+impl ExprKind {
+    pub fn accepts(&self, other: &ExprKind) -> bool {
+        match (self, other) {
+            (ExprKind::Of(a), ExprKind::Of(b)) => a == b,
+            _ => true,
+        }
+    }
+}
+
+impl LogScale {
+    pub fn to_linear(&self, level: Measured) -> Measured {
+        // level -> x / ref
+        let x = self.base.powf(level.v / self.mul);
+        Measured::new(x, x * self.base.ln() / self.mul * level.u)
+    }
+
+    pub fn to_level(&self, ratio: Measured) -> Measured {
+        // x / ref -> level
+        let l = self.mul * ratio.v.log(self.base);
+        if ratio.u == 0.0 {
+            return Measured::exact(l);
+        }
+        Measured::new(l, self.mul / self.base.ln() * ratio.u / ratio.v)
+    }
 }
 
 impl PartialEq for Unit {
@@ -50,9 +182,20 @@ pub struct BaseUnits {
     // - unprefix,
     // - move to numerator(change pow sign, invert multiplier),
     // - recalc multiplier of unit
-    pub v: f64,
+    pub v: Measured,
     pub units: HashMap<String, Unit>,
     pub mpl: f64,
+    // относительная неопределенность mpl, см. Unit::unc
+    #[serde(default)]
+    pub mpl_unc: f64,
+    // опорный уровень задан mpl и units, см. Unit::log
+    #[serde(default)]
+    pub log: Option<LogScale>,
+    #[serde(default)]
+    pub kind: ExprKind,
+    // словари "name@version", из которых взяты единицы результата
+    #[serde(default)]
+    pub vocs: BTreeSet<String>,
 }
 
 impl Default for BaseUnits {
@@ -64,47 +207,58 @@ impl Default for BaseUnits {
 impl BaseUnits {
     pub fn new() -> BaseUnits {
         Self {
-            v: 0.0,
+            v: Measured::exact(0.0),
             units: HashMap::new(),
             mpl: 1.0,
+            mpl_unc: 0.0,
+            log: None,
+            kind: ExprKind::Any,
+            vocs: BTreeSet::new(),
         }
     }
 
     pub fn as_readable(&self) -> String {
-        log_data(&Vec::from_iter(self.units.values().cloned()))
+        log_data(&self.canonical())
+    }
+
+    pub fn canonical(&self) -> Vec<Unit> {
+        canonical(self.units.values().cloned())
     }
 
 
-    pub fn merge_one(&mut self, bu: BaseUnits, tag: String) {
+    pub fn merge_one(&mut self, bu: BaseUnits, tag: String) -> Result<(), UnitsError> {
         let u = bu.units.get(&tag).unwrap();
 
         match self.units.get_mut(&tag) {
             Some(unit) => {
                 unit.mpl *= u.mpl;
-                unit.pow += u.pow;
+                unit.pow = add_pow(unit.pow, u.pow, &tag)?;
             }
             None => {
                 self.units.insert(tag.clone(), u.clone());
             }
         }
+        Ok(())
     }
 
-    fn merge_base_one(&mut self, bu: Unit) {
+    fn merge_base_one(&mut self, bu: Unit) -> Result<(), UnitsError> {
         match self.units.get_mut(&bu.tag) {
             Some(unit) => {
                 unit.mpl *= bu.mpl;
-                unit.pow += bu.pow;
+                unit.pow = add_pow(unit.pow, bu.pow, &bu.tag)?;
             }
             None => {
                 self.units.insert(bu.tag.clone(), bu.clone());
             }
         }
+        Ok(())
     }
 
-    pub fn reduce(mut self) -> Self {
+    pub fn reduce(mut self) -> Result<Self, UnitsError> {
         let mut units: Vec<Unit> = vec![];
         let mut mpl: f64 = 1.0;
-        let voc = register::units();
+        let mut unc: f64 = 0.0;
+        let mut voc = register::units();
 
         for u in self.units.values() {
             // x^0 == 1 - no need to reduce
@@ -115,28 +269,31 @@ impl BaseUnits {
                 continue;
             }
 
-            let (m, b_units) = to_bases(u, &voc);
-            units.extend(b_units);
-            mpl *= m;
+            let reduced = voc.reduce_unit(u)?;
+            units.extend_from_slice(&reduced.units);
+            self.vocs.extend(reduced.vocs.iter().cloned());
+            mpl *= reduced.mpl * u.mpl;
+            unc = unc.hypot(reduced.unc);
         }
         // println!("{:?}", units);
         self.units.clear();
 
         // fill from scratch with totally bases
         self.mpl = mpl;
+        self.mpl_unc = unc;
         for u in units {
-            self.merge_base_one(u)
-        };
+            self.merge_base_one(u)?;
+        }
 
         self.exclude_zero_powered();
-        self
+        Ok(self)
     }
 
     fn exclude_zero_powered(&mut self) {
         let mut to_remove: Vec<String> = vec![];
 
         for u in self.units.values().cloned() {
-            if u.pow == 0 {
+            if u.pow == 0 || u.tag == UNITY {
                 to_remove.push(u.tag);
             }
         }
@@ -146,13 +303,47 @@ impl BaseUnits {
         }
     }
 
+    pub fn expr_kind(&self) -> ExprKind {
+        // вызывается до reduce, пока units - это разобранные единицы выражения
+        if self.units.values().all(|u| u.base.is_empty()) {
+            return ExprKind::Any;
+        }
+        match Vec::from_iter(self.units.values()).as_slice() {
+            [u] if u.pow == 1 => u.kind.clone().map_or(ExprKind::Unknown, ExprKind::Of),
+            _ => self.derived_kind(),
+        }
+    }
+
+    fn derived_kind(&self) -> ExprKind {
+        // род составного выражения - только объявленный в DERIVED_KINDS:
+        // Н/м^2 = force*length^-2 -> pressure; базовые единицы - по SI_KINDS
+        let mut kinds: BTreeMap<&str, i32> = BTreeMap::new();
+        for u in self.units.values() {
+            let kind = match (&u.kind, SI_ORDER.iter().position(|t| *t == u.tag)) {
+                (Some(k), _) => k.as_str(),
+                (None, Some(i)) if u.base.is_empty() => SI_KINDS[i],
+                _ => return ExprKind::Unknown,
+            };
+            *kinds.entry(kind).or_default() += u.pow as i32;
+        }
+        kinds.retain(|_, p| *p != 0);
+        DERIVED_KINDS.iter()
+            .find(|(expr, _)| expr.len() == kinds.len() && expr.iter().all(|(k, p)| kinds.get(k) == Some(&(*p as i32))))
+            .map_or(ExprKind::Unknown, |(_, kind)| ExprKind::Of(kind.to_string()))
+    }
+
+    pub fn erase_pseudo(&mut self) {
+        // рад и ср безразмерны: Гц <=> рад/с
+        self.units.retain(|_, u| !u.pseudo);
+    }
+
     pub fn add_parsed_unit(&mut self, u: ParsedUnit) -> Result<(), UnitsError> {
         // add dto as unit
 
-        let mut all_units: MutexGuard<HashMap<String, Unit>> = units();
+        let all_units: MutexGuard<Registry> = units();
         let all_prefixes: HashMap<&'static str, i32> = prefixes();
 
-        match all_units.get_mut(&u.tag) {
+        match all_units.get(&u.tag) {
             Some(ex_u) => {
                 let mut proto = ex_u.clone();
                 // println!("{:?}", ex_u);
@@ -161,8 +352,7 @@ impl BaseUnits {
                 let mut pow = u.pow;
 
                 // если есть приставка - считаем мультипликатор
-                if u.pfx.is_some() {
-                    let pfx = u.pfx.unwrap();
+                if let Some(pfx) = u.pfx {
                     mpl = match all_prefixes.get(pfx.as_str()) {
                         Some(pfx_pow) => 10f64.powi(*pfx_pow),
                         None => return Err(UnitsError::NoUnitPrefix(pfx)),
@@ -174,7 +364,7 @@ impl BaseUnits {
                 // если знаменатель
                 if u.den {
                     mpl = 1.0 / mpl;
-                    pow = -pow
+                    pow = pow.checked_neg().ok_or_else(|| UnitsError::PowOverflow(u.tag.clone()))?;
                 };
 
                 proto.mpl = mpl;
@@ -183,7 +373,7 @@ impl BaseUnits {
                 match self.units.get_mut(&proto.tag) {
                     Some(ex_u) => {
                         ex_u.mpl *= proto.mpl;
-                        ex_u.pow += proto.pow;
+                        ex_u.pow = add_pow(ex_u.pow, proto.pow, &proto.tag)?;
                     }
                     None => {
                         self.units.insert(proto.tag.clone(), proto);
@@ -201,73 +391,243 @@ impl BaseUnits {
     }
 }
 
-pub fn to_bases(u: &Unit, voc: &HashMap<String, Unit>) -> (f64, Vec<Unit>) {
+fn add_pow(a: i8, b: i8, tag: &str) -> Result<i8, UnitsError> {
+    // степени i8: м^100*м^100 - ошибка, а не переполнение
+    a.checked_add(b).ok_or_else(|| UnitsError::PowOverflow(tag.to_owned()))
+}
+
+pub fn to_bases(u: &Unit, voc: &HashMap<String, Unit>) -> Result<(f64, Vec<Unit>), UnitsError> {
     // u - единица {mpl, tag, pow, base} к-ю надо представить в базовых
+    reduce_traced(u, voc, None)
+}
 
+/// to_bases с записью шагов раскрытия определений в `steps` (explain)
+pub(crate) fn reduce_traced(u: &Unit, voc: &HashMap<String, Unit>, steps: Option<&mut Vec<Trace>>) -> Result<(f64, Vec<Unit>), UnitsError> {
+    if u.base.is_empty() {
+        return Ok((u.mpl, vec![u.clone()]));
+    }
     let mut base_units: Vec<Unit> = vec![];
-    let mut mpl: f64 = 1.0;
+    let mpl = expand_base(&u.base, u.pow, voc, &mut base_units, steps)?;
+    Ok((mpl * u.mpl, base_units))
+}
 
-    if u.base.is_empty() {
-        mpl *= u.mpl; //.powi(u.pow as i32);
-        // u.mpl = 1; ???
-        // mpl *= u.mpl.powi(u.pow as i32);
-        return (mpl, vec![u.clone()]);
+fn expand_base(base: &[Unit], pow: i8, voc: &HashMap<String, Unit>, out: &mut Vec<Unit>, mut steps: Option<&mut Vec<Trace>>) -> Result<f64, UnitsError> {
+    // tag^pow = П (c.mpl^pow * c.tag^(c.pow*pow)), рекурсивно до базовых единиц
+    let mut mpl = 1.0;
+    for c in base {
+        let m = c.mpl.powi(pow as i32);
+        let c_pow = c.pow.checked_mul(pow).ok_or_else(|| UnitsError::PowOverflow(c.tag.clone()))?;
+        let mut sub_steps = steps.is_some().then(Vec::new);
+
+        let (sub, step) = match voc.get(&c.tag) {
+            Some(v) if !v.base.is_empty() => {
+                (expand_base(&v.base, c_pow, voc, out, sub_steps.as_mut())?, Step::Define { tag: c.tag.clone(), pow: c_pow })
+            }
+            Some(v) => {
+                // базовая единица словаря: kind, pseudo и т.п. сохраняются
+                let mut unit = v.clone();
+                unit.pow = c_pow;
+                out.push(unit);
+                (1.0, Step::Base { tag: c.tag.clone(), pow: c_pow })
+            }
+            None => (1.0, Step::Base { tag: c.tag.clone(), pow: c_pow }),
+        };
+        mpl *= m * sub;
+
+        if let Some(steps) = steps.as_mut() {
+            let child = Trace::new(step, sub, sub_steps.unwrap_or_default());
+            let step = Step::Power { tag: c.tag.clone(), mpl: c.mpl, pow };
+            steps.push(Trace::new(step, m * sub, vec![child]));
+        }
     }
+    Ok(mpl)
+}
 
+pub fn base_from_expr(v: f64, e: &Expr) -> Result<Vec<Unit>, UnitsError> {
+    // unit_expr -> Unit.base, как в словарях: [{tag, mpl, pow}]
+    // приставки переходят в mpl, значение v - в mpl первой единицы
+    let units = match e {
+        Expr::Fraction { up, down } => up.iter().chain(down.iter()).collect(),
+        Expr::Unit { .. } => vec![e],
+        Expr::Convert(..) => return Err(UnitsError::NoUnit("sorry".into())),
+    };
+
+    let mut base = vec![];
+    for u in units {
+        if let Expr::Unit { pfx, tag, pow, den } = u {
+            let mut mpl = match pfx {
+                Some(p) => match prefixes().get(p.as_str()) {
+                    Some(exp) => 10f64.powi(*exp),
+                    None => return Err(UnitsError::NoUnitPrefix(p.clone())),
+                },
+                None => 1.0,
+            };
+            let pow = match den {
+                true => pow.checked_neg().ok_or_else(|| UnitsError::PowOverflow(tag.clone()))?,
+                false => *pow,
+            };
+            mpl = mpl.powi(pow as i32);
+            base.push(Unit::new(tag, mpl, pow));
+        }
+    }
+    if let Some(first) = base.first_mut() {
+        first.mpl *= v;
+    }
+    Ok(base)
+}
 
-    base_units.push(u.clone());
+pub fn base_from_dsl(def: &str) -> Result<(Value, Vec<Unit>), Box<dyn Error>> {
+    // "9.80665 кг*м/с^2" -> Unit.base; значение можно опустить: "кг*м/с^2"
+    let (val, expr) = match def.trim().split_once(WS) {
+        Some((val, expr)) => (parse_value(val)?, expr.trim()),
+        None => (Value { v: 1.0, u: None }, def.trim()),
+    };
+    if expr.contains(WS) {
+        return Err(Box::new(ParseError::TooMuchSpaces(def.matches(WS).count())));
+    }
+    let base = base_from_expr(val.v, &parse_expr(expr)?)?;
+    Ok((val, base))
+}
 
-    // reduce to totally base
-    loop {
-        let mut new_base_units: Vec<Unit> = vec![];
+fn de_base<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<Unit>, D::Error> {
+    // base - массив единиц или строка DSL, разбирается при загрузке словаря
+    struct BaseVisitor;
 
-        // log_data(&base_units, &mpl);
+    impl<'de> Visitor<'de> for BaseVisitor {
+        type Value = Vec<Unit>;
 
-        for p in &mut base_units {
-            if !p.base.is_empty() {
-                // apply parent pow to child
-                for c in &mut p.base {
-                    c.pow *= p.pow;
-                    c.mpl = c.mpl.powi(p.pow as i32);
-                }
-                // println!("{:?}", p.base);
-                new_base_units.extend(p.base.clone());
-                continue;
-            }
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "array of units or unit expression like \"9.80665 кг*м/с^2\"")
+        }
 
-            // get from voc
-            if let Some(voc_unit) = voc.get(&p.tag) {
-                let mut unit = voc_unit.clone();
-                if !p.base.is_empty() {
-                    // apply parent pow to child
-                    for c in &mut unit.base {
-                        c.pow *= p.pow;
-                        c.mpl = c.mpl.powi(p.pow as i32);
-                    }
-                    // println!("{:?}", unit.base);
-                    new_base_units.extend(unit.base);
-                } else {
-                    unit.pow *= p.pow;
-                    unit.mpl = unit.mpl.powi(p.pow as i32);
-                    new_base_units.push(unit);
-                }
+        fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+            Vec::<Unit>::deserialize(SeqAccessDeserializer::new(seq))
+        }
+
+        fn visit_str<E: de::Error>(self, s: &str) -> Result<Self::Value, E> {
+            let (val, base) = base_from_dsl(s).map_err(E::custom)?;
+            if val.u.is_some() {
+                return Err(E::custom(format!("uncertainty in base is not allowed, use unc: {s}")));
             }
-            mpl *= p.mpl;
-        };
+            Ok(base)
+        }
+    }
+
+    d.deserialize_any(BaseVisitor)
+}
 
-        // if !new_base_units.is_empty() {
-        if new_base_units != base_units {
-            base_units = new_base_units;
-        } else { break; }
+pub fn rel_uncertainty(tag: &str, voc: &HashMap<String, Unit>) -> f64 {
+    // относительная неопределенность определения единицы вместе с её базой
+    match voc.get(tag) {
+        Some(u) => u.base.iter().fold(u.unc, |acc, c| {
+            acc.hypot(c.pow as f64 * rel_uncertainty(&c.tag, voc))
+        }),
+        None => 0.0,
     }
+}
+
+pub fn source_vocabularies(tag: &str, voc: &HashMap<String, Unit>, acc: &mut BTreeSet<String>) {
+    // словари, из которых взяты единица и вся цепочка её определения
+    if let Some(u) = voc.get(tag) {
+        acc.extend(u.voc.clone());
+        for c in &u.base {
+            source_vocabularies(&c.tag, voc, acc);
+        }
+    }
+}
+
+// порядок основных единиц СИ в нормальной форме
+pub const SI_ORDER: [&str; 7] = ["м", "кг", "с", "А", "К", "моль", "кд"];
+
+// род величины базовых единиц, в порядке SI_ORDER
+const SI_KINDS: [&str; 7] = ["length", "mass", "time", "current", "temperature", "amount", "luminous_intensity"];
+
+//@fmt:off
+// объявленные рода составных выражений: произведение родов в степенях
+const DERIVED_KINDS: [(&[(&str, i8)], &str); 5] = [
+    (&[("force", 1), ("length", -2)],  "pressure"),
+    (&[("force", 1), ("length", 1)],   "torque"),
+    (&[("energy", 1), ("time", -1)],   "power"),
+    (&[("power", 1), ("time", 1)],     "energy"),
+    (&[("energy", 1), ("length", -3)], "energy_density"),
+];
+//@fmt:on
+
+fn canonical_key(tag: &str, pow: i32) -> (bool, usize, &str) {
+    // положительные степени раньше отрицательных, затем порядок СИ, затем по тегу
+    let si = SI_ORDER.iter().position(|t| *t == tag).unwrap_or(SI_ORDER.len());
+    (pow < 0, si, tag)
+}
 
-    mpl *= u.mpl;
-    // println!("{:?}", base_units);
-    (mpl, base_units)
+/// Нормальная форма: одноимённые единицы объединены, нулевые степени убраны,
+/// порядок - см. canonical_key
+pub fn canonical(units: impl IntoIterator<Item = Unit>) -> Vec<Unit> {
+    let mut res: Vec<Unit> = vec![];
+    for u in units {
+        match res.iter_mut().find(|r| r.tag == u.tag) {
+            Some(r) => {
+                r.mpl *= u.mpl;
+                r.pow += u.pow;
+            }
+            None => res.push(u),
+        }
+    }
+    res.retain(|u| u.pow != 0);
+    res.sort_by(|a, b| canonical_key(&a.tag, a.pow as i32).cmp(&canonical_key(&b.tag, b.pow as i32)));
+    res
 }
 
+/// Нормальная форма выражения DSL: "с^-2*кг/м" -> "кг/м*с^2"
+pub fn normalize(expr: &str) -> Result<String, ParseError> {
+    let units = match parse_expr(expr.trim())? {
+        Expr::Fraction { up, down } => up.into_iter().chain(down).collect(),
+        e => vec![e],
+    };
+
+    // (приставка, тег, степень); приставка - часть имени единицы
+    let mut res: Vec<(Option<String>, String, i32)> = vec![];
+    for u in units {
+        let Expr::Unit { pfx, tag, pow, den } = u else { continue };
+        let pow = if den { -(pow as i32) } else { pow as i32 };
+        match res.iter_mut().find(|(p, t, _)| *p == pfx && *t == tag) {
+            Some((.., p)) => *p += pow,
+            None => res.push((pfx, tag, pow)),
+        }
+    }
+    res.retain(|(.., pow)| *pow != 0);
+    // результат должен снова разбираться: степень в пределах i8
+    if let Some((pfx, tag, pow)) = res.iter().find(|(.., pow)| i8::try_from(*pow).is_err()) {
+        let unit = pfx.as_ref().map_or(tag.clone(), |p| format!("{p}_{tag}"));
+        return Err(ParseError::WrongPow(format!("{unit}{PW}{pow}")));
+    }
+    res.sort_by(|(pa, a, x), (pb, b, y)| canonical_key(a, *x).cmp(&canonical_key(b, *y)).then(pa.cmp(pb)));
+
+    let show = |(pfx, tag, pow): &(Option<String>, String, i32)| {
+        let unit = match pfx {
+            Some(p) => format!("{p}_{tag}"),
+            None => tag.clone(),
+        };
+        match pow.abs() {
+            1 => unit,
+            p => format!("{unit}{PW}{p}"),
+        }
+    };
+    let (up, down): (Vec<_>, Vec<_>) = res.iter().partition(|(.., pow)| *pow > 0);
+    let up = up.into_iter().map(show).collect::<Vec<_>>().join(MUL);
+    let down = down.into_iter().map(show).collect::<Vec<_>>().join(MUL);
+
+    Ok(match (up.is_empty(), down.is_empty()) {
+        (true, true) => UNITY.to_owned(),
+        (_, true) => up,
+        (true, false) => format!("{UNITY}{DIV}{down}"),
+        (false, false) => format!("{up}{DIV}{down}"),
+    })
+}
 
 pub fn log_data(u: &[Unit]) -> String {
+    if u.is_empty() {
+        return format!("[{}]", UNITY);
+    }
     let s = u.iter().map(|s| format!("{}^{}", s.tag, s.pow))
         .collect::<Vec<_>>().join(" * ");
     // format!("{} [{}]", m, s)
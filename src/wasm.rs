@@ -0,0 +1,90 @@
+// Wasm: JS API для браузера, словари вшиты в модуль (register::init_embedded_units)
+//
+// wasm-pack build --target web --features wasm
+//
+// import init, { convert, decompose, validate, listUnits } from "./pkg/fold_pattern.js";
+// await init();
+// convert("1 к_Па=>атм");      // 0.009869232667
+// decompose("ат");             // '{"mpl":98066.5,"units":{"кг":1,...},"result":"..."}'
+// validate("1 м=>с");          // "NotCoherent: ..." или undefined
+// compatibleUnits("Па", false); // '[{"tag":"ат","factor":1.0197e-5},...]'
+// complete("1 к_П");           // '{"state":"PrefixedUnit","token":"к_П","candidates":[...]}'
+
+use crate::common::{decompose_json, error_kind};
+use crate::complete;
+use crate::interpreter::Interpreter;
+use crate::register::{init_embedded_units, prefixes, units};
+use std::cell::Cell;
+use std::error::Error;
+use wasm_bindgen::prelude::*;
+
+thread_local! {
+    static II: Cell<Option<Interpreter>> = const { Cell::new(None) };
+}
+
+fn with_ii<T>(f: impl FnOnce(&mut Interpreter) -> T) -> T {
+    // на wasm32 паника - это trap (panic=abort), catch_unwind не поможет; ошибки ввода
+    // парсер возвращает как Err. Интерпретатор вынимается на время вызова, так что
+    // после trap следующий вызов создаст новый вместо "already borrowed"
+    let mut ii = II.take().unwrap_or_else(|| {
+        init_embedded_units();
+        Interpreter::new()
+    });
+    let res = f(&mut ii);
+    II.set(Some(ii));
+    res
+}
+
+fn js_error(e: Box<dyn Error>) -> JsError {
+    JsError::new(&format!("{}: {e}", error_kind(e.as_ref())))
+}
+
+/// Конвертирует "1 к_Па=>атм", возвращает число
+#[wasm_bindgen]
+pub fn convert(stmt: &str) -> Result<f64, JsError> {
+    with_ii(|ii| ii.try_conv_f64(stmt)).map_err(js_error)
+}
+
+/// Раскладывает выражение на базовые единицы, результат - JSON-строка
+#[wasm_bindgen]
+pub fn decompose(expr: &str) -> Result<String, JsError> {
+    with_ii(|ii| decompose_json(ii, expr))
+        .map(|v| v.to_string())
+        .map_err(js_error)
+}
+
+/// Проверка ввода для форм: текст ошибки или undefined, если выражение корректно
+#[wasm_bindgen]
+pub fn validate(stmt: &str) -> Option<String> {
+    with_ii(|ii| ii.explain(stmt).map(|_| ()))
+        .err()
+        .map(|e| format!("{}: {e}", error_kind(e.as_ref())))
+}
+
+/// Подсказки для частичного ввода, результат - JSON-строка
+#[wasm_bindgen]
+pub fn complete(input: &str) -> String {
+    init_embedded_units();
+    serde_json::to_string(&complete::complete(input)).unwrap_or_default()
+}
+
+/// Единицы той же размерности с множителями, результат - JSON-строка
+#[wasm_bindgen(js_name = compatibleUnits)]
+pub fn compatible_units(expr: &str, prefixed: bool) -> Result<String, JsError> {
+    with_ii(|ii| ii.compatible_units(expr, prefixed))
+        .map(|v| serde_json::to_string(&v).unwrap_or_default())
+        .map_err(js_error)
+}
+
+#[wasm_bindgen(js_name = listUnits)]
+pub fn list_units() -> Vec<String> {
+    init_embedded_units();
+    let mut tags: Vec<String> = units().keys().cloned().collect();
+    tags.sort();
+    tags
+}
+
+#[wasm_bindgen(js_name = listPrefixes)]
+pub fn list_prefixes() -> Vec<String> {
+    prefixes().keys().map(|p| p.to_string()).collect()
+}